@@ -0,0 +1,206 @@
+use crate::ast::{self, AssignOperator, Position, Span, Statement};
+use crate::configuration::Configuration;
+use crate::refactor::{apply_edits, TextEdit};
+
+/// Align `=` of consecutive assignments and `#` of consecutive trailing
+/// comments in formatted text, returns the text unchanged when it can not be
+/// parsed.
+pub fn align(text: &str, config: &Configuration) -> String {
+    let mut text = text.to_string();
+    if config.align_assignments {
+        if let Ok(file) = ast::parse_file(&text) {
+            let mut edits = vec![];
+            align_assignments(&text, &file.statements, &mut edits);
+            text = apply_edits(&text, &edits);
+        }
+    }
+    if config.align_trailing_comments {
+        if let Ok(comments) = ast::parse_comments(&text) {
+            text = apply_edits(&text, &align_comments(&text, &comments));
+        }
+    }
+    text
+}
+
+/// single line assignment, returns the span of the target and the operator
+fn assignment(statement: &Statement) -> Option<(Span, &'static str)> {
+    match statement {
+        Statement::Assignment {
+            target,
+            operator,
+            span,
+            ..
+        } if span.start.line == span.end.line => {
+            let operator = match operator {
+                AssignOperator::Assign => "=",
+                AssignOperator::AddAssign => "+=",
+            };
+            Some((target.span, operator))
+        }
+        _ => None,
+    }
+}
+
+/// pad targets of assignments on consecutive lines so that their `=` are in
+/// one column, multiline assignments break the group
+fn align_assignments(code: &str, statements: &[Statement], edits: &mut Vec<TextEdit>) {
+    // target, operator and value of each assignment in the group
+    let mut group: Vec<(Span, &str, Span)> = vec![];
+    for statement in statements {
+        match (assignment(statement), statement) {
+            (Some((target, operator)), Statement::Assignment { value, .. }) => {
+                let adjacent = group
+                    .last()
+                    .is_none_or(|(last, ..)| last.start.line + 1 == target.start.line);
+                if !adjacent {
+                    pad_targets(code, &mut group, edits);
+                }
+                group.push((target, operator, value.span));
+            }
+            _ => pad_targets(code, &mut group, edits),
+        }
+        match statement {
+            Statement::If {
+                branches,
+                else_block,
+                ..
+            } => {
+                for (_, body) in branches {
+                    align_assignments(code, body, edits);
+                }
+                if let Some(body) = else_block {
+                    align_assignments(code, body, edits);
+                }
+            }
+            Statement::Foreach { body, .. } => align_assignments(code, body, edits),
+            _ => {}
+        }
+    }
+    pad_targets(code, &mut group, edits);
+}
+
+fn pad_targets(code: &str, group: &mut Vec<(Span, &str, Span)>, edits: &mut Vec<TextEdit>) {
+    let width = |(target, operator, _): &(Span, &str, Span)| {
+        target.text(code).chars().count() + operator.len()
+    };
+    let max = group.iter().map(width).max().unwrap_or(0);
+    for item in group.drain(..) {
+        let (target, operator, value) = item;
+        let gap = Span {
+            start: target.end,
+            end: value.start,
+        };
+        let new_text = format!("{}{} ", " ".repeat(1 + max - width(&item)), operator);
+        if gap.text(code) != new_text {
+            edits.push(TextEdit {
+                span: gap,
+                new_text,
+            });
+        }
+    }
+}
+
+/// pad code before trailing comments on consecutive lines so that their `#`
+/// are in one column
+fn align_comments(code: &str, comments: &[Span]) -> Vec<TextEdit> {
+    // the whitespace before each trailing comment and the width of the code
+    let mut trailing = vec![];
+    for comment in comments {
+        let line_start = code[..comment.start.offset]
+            .rfind('\n')
+            .map_or(0, |i| i + 1);
+        let before = &code[line_start..comment.start.offset];
+        let content = before.trim_end();
+        if content.trim_start().is_empty() {
+            continue;
+        }
+        let gap = Span {
+            start: Position {
+                offset: line_start + content.len(),
+                ..comment.start
+            },
+            end: comment.start,
+        };
+        trailing.push((gap, content.chars().count()));
+    }
+
+    let mut edits = vec![];
+    let mut rest = trailing.as_slice();
+    while !rest.is_empty() {
+        let len = 1 + rest
+            .windows(2)
+            .take_while(|pair| pair[0].0.start.line + 1 == pair[1].0.start.line)
+            .count();
+        let (group, tail) = rest.split_at(len);
+        rest = tail;
+        if group.len() < 2 {
+            continue;
+        }
+        let max = group.iter().map(|(_, width)| *width).max().unwrap_or(0);
+        for (gap, width) in group {
+            let new_text = " ".repeat(1 + max - width);
+            if gap.text(code) != new_text {
+                edits.push(TextEdit {
+                    span: *gap,
+                    new_text,
+                });
+            }
+        }
+    }
+    edits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::DEFAULT_CONFIGURATION;
+
+    #[test]
+    fn align_assignment_groups() {
+        let config = Configuration {
+            align_assignments: true,
+            ..DEFAULT_CONFIGURATION
+        };
+        for (src, dst) in [
+            ("a = 1\nbcd = 2\n", "a   = 1\nbcd = 2\n"),
+            ("a = 1\nbc += 2\n", "a   = 1\nbc += 2\n"),
+            ("a = 1\n\nbcd = 2\n", "a = 1\n\nbcd = 2\n"),
+            ("a = 1\n# c\nbcd = 2\n", "a = 1\n# c\nbcd = 2\n"),
+            ("a = 1\nf()\nbcd = 2\n", "a = 1\nf()\nbcd = 2\n"),
+            (
+                "a = 1\nbb = [\n  1,\n]\nc = 2\n",
+                "a = 1\nbb = [\n  1,\n]\nc = 2\n",
+            ),
+            (
+                "if x\n  a = 1\n  bb = 2\nendif\nccc = 3\n",
+                "if x\n  a  = 1\n  bb = 2\nendif\nccc = 3\n",
+            ),
+            ("a = '='\nbb = 2 # = c\n", "a  = '='\nbb = 2 # = c\n"),
+        ] {
+            assert_eq!(align(src, &config), dst, "{}", src);
+        }
+    }
+
+    #[test]
+    fn align_comment_groups() {
+        let config = Configuration {
+            align_trailing_comments: true,
+            ..DEFAULT_CONFIGURATION
+        };
+        for (src, dst) in [
+            ("a = 1 # x\nbcd = 2 # y\n", "a = 1   # x\nbcd = 2 # y\n"),
+            (
+                "a = 1 # x\n# y\nbcd = 2 # z\n",
+                "a = 1 # x\n# y\nbcd = 2 # z\n",
+            ),
+            ("a = 1 # x\n\nbcd = 2 # y\n", "a = 1 # x\n\nbcd = 2 # y\n"),
+            (
+                "x = [\n  1, # a\n  222, # b\n]\n",
+                "x = [\n  1,   # a\n  222, # b\n]\n",
+            ),
+            ("a = '#' # x\nbc = 2 # y\n", "a = '#' # x\nbc = 2  # y\n"),
+        ] {
+            assert_eq!(align(src, &config), dst, "{}", src);
+        }
+    }
+}
@@ -0,0 +1,1181 @@
+use crate::parser::ParseError;
+
+type Result<T> = std::result::Result<T, ParseError>;
+
+/// parse text to syntax tree, which the checks and refactorings work on while
+/// `parser` formats without one
+pub fn parse_file(text: &str) -> Result<File> {
+    let tokens = Lexer::new(text).tokenize()?;
+    TreeParser { tokens, index: 0 }.parse_file()
+}
+
+/// spans of the comments in text, from `#` to the end of line
+pub fn parse_comments(text: &str) -> Result<Vec<Span>> {
+    let mut lexer = Lexer::new(text);
+    lexer.tokenize()?;
+    Ok(lexer.comments)
+}
+
+/// the depth of `( [ {` at the beginning of each line, the first line is at
+/// index 0. Meson only allows line breaks where it is above 0, or inside
+/// `'''` strings.
+pub fn line_depths(text: &str) -> Result<Vec<usize>> {
+    let mut lexer = Lexer::new(text);
+    lexer.tokenize()?;
+    Ok(lexer.line_depths)
+}
+
+/// location in source text, `line` and `column` start at 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Position {
+    /// byte offset
+    pub offset: usize,
+    pub line: usize,
+    /// counted in chars
+    pub column: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+impl Span {
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            start: self.start,
+            end: other.end,
+        }
+    }
+    pub fn contains(&self, offset: usize) -> bool {
+        self.start.offset <= offset && offset <= self.end.offset
+    }
+    pub fn text<'a>(&self, source: &'a str) -> &'a str {
+        &source[self.start.offset..self.end.offset]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct File {
+    pub statements: Vec<Statement>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ident {
+    pub name: String,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    Expression(Expr),
+    Assignment {
+        target: Ident,
+        operator: AssignOperator,
+        value: Expr,
+        span: Span,
+    },
+    If {
+        /// `if` and `elif` branches
+        branches: Vec<(Expr, Vec<Statement>)>,
+        else_block: Option<Vec<Statement>>,
+        span: Span,
+    },
+    Foreach {
+        variables: Vec<Ident>,
+        items: Expr,
+        body: Vec<Statement>,
+        span: Span,
+    },
+    Break(Span),
+    Continue(Span),
+}
+impl Statement {
+    pub fn span(&self) -> Span {
+        match self {
+            Self::Expression(e) => e.span,
+            Self::Assignment { span, .. } | Self::If { span, .. } | Self::Foreach { span, .. } => {
+                *span
+            }
+            Self::Break(span) | Self::Continue(span) => *span,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssignOperator {
+    /// `=`
+    Assign,
+    /// `+=`
+    AddAssign,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind {
+    Identifier(String),
+    Bool(bool),
+    Number(i64),
+    String(StringLiteral),
+    Array(Vec<Expr>),
+    Dictionary(Vec<(Expr, Expr)>),
+    Call {
+        name: Ident,
+        arguments: Arguments,
+    },
+    MethodCall {
+        object: Box<Expr>,
+        name: Ident,
+        arguments: Arguments,
+    },
+    Index {
+        object: Box<Expr>,
+        index: Box<Expr>,
+    },
+    Unary {
+        operator: UnaryOperator,
+        operand: Box<Expr>,
+    },
+    Binary {
+        operator: BinaryOperator,
+        left: Box<Expr>,
+        right: Box<Expr>,
+    },
+    Ternary {
+        condition: Box<Expr>,
+        if_true: Box<Expr>,
+        if_false: Box<Expr>,
+    },
+    Parenthesized(Box<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StringLiteral {
+    /// content with escapes resolved
+    pub value: String,
+    /// `f'...'`
+    pub format: bool,
+    /// `'''...'''`
+    pub multiline: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Arguments {
+    pub positional: Vec<Expr>,
+    pub keywords: Vec<KeywordArgument>,
+    pub span: Span,
+}
+impl Arguments {
+    pub fn keyword(&self, name: &str) -> Option<&Expr> {
+        self.keywords
+            .iter()
+            .find(|k| k.name.name == name)
+            .map(|k| &k.value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeywordArgument {
+    pub name: Ident,
+    pub value: Expr,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOperator {
+    Not,
+    Negative,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOperator {
+    Or,
+    And,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    In,
+    NotIn,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+}
+impl BinaryOperator {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Or => "or",
+            Self::And => "and",
+            Self::Equal => "==",
+            Self::NotEqual => "!=",
+            Self::Less => "<",
+            Self::LessEqual => "<=",
+            Self::Greater => ">",
+            Self::GreaterEqual => ">=",
+            Self::In => "in",
+            Self::NotIn => "not in",
+            Self::Add => "+",
+            Self::Subtract => "-",
+            Self::Multiply => "*",
+            Self::Divide => "/",
+            Self::Modulo => "%",
+        }
+    }
+
+    /// binding power, higher binds tighter
+    pub fn precedence(self) -> usize {
+        match self {
+            Self::Or => 0,
+            Self::And => 1,
+            Self::Equal
+            | Self::NotEqual
+            | Self::Less
+            | Self::LessEqual
+            | Self::Greater
+            | Self::GreaterEqual
+            | Self::In
+            | Self::NotIn => 2,
+            Self::Add | Self::Subtract => 3,
+            Self::Multiply | Self::Divide | Self::Modulo => 4,
+        }
+    }
+}
+
+impl ExprKind {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(s) => Some(&s.value),
+            _ => None,
+        }
+    }
+    pub fn as_identifier(&self) -> Option<&str> {
+        match self {
+            Self::Identifier(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+/// walk the syntax tree, every method returns whether to visit children
+pub trait Visitor {
+    fn visit_statement(&mut self, _statement: &Statement) -> bool {
+        true
+    }
+    fn visit_expr(&mut self, _expr: &Expr) -> bool {
+        true
+    }
+    fn leave_statement(&mut self, _statement: &Statement) {}
+}
+
+pub fn walk_statements<V: Visitor>(visitor: &mut V, statements: &[Statement]) {
+    for statement in statements {
+        walk_statement(visitor, statement);
+    }
+}
+
+pub fn walk_statement<V: Visitor>(visitor: &mut V, statement: &Statement) {
+    if visitor.visit_statement(statement) {
+        match statement {
+            Statement::Expression(e) => walk_expr(visitor, e),
+            Statement::Assignment { value, .. } => walk_expr(visitor, value),
+            Statement::If {
+                branches,
+                else_block,
+                ..
+            } => {
+                for (condition, block) in branches {
+                    walk_expr(visitor, condition);
+                    walk_statements(visitor, block);
+                }
+                if let Some(block) = else_block {
+                    walk_statements(visitor, block);
+                }
+            }
+            Statement::Foreach { items, body, .. } => {
+                walk_expr(visitor, items);
+                walk_statements(visitor, body);
+            }
+            Statement::Break(_) | Statement::Continue(_) => {}
+        }
+    }
+    visitor.leave_statement(statement);
+}
+
+pub fn walk_expr<V: Visitor>(visitor: &mut V, expr: &Expr) {
+    if !visitor.visit_expr(expr) {
+        return;
+    }
+    let walk_arguments = |visitor: &mut V, arguments: &Arguments| {
+        for e in &arguments.positional {
+            walk_expr(visitor, e);
+        }
+        for k in &arguments.keywords {
+            walk_expr(visitor, &k.value);
+        }
+    };
+    match &expr.kind {
+        ExprKind::Identifier(_) | ExprKind::Bool(_) | ExprKind::Number(_) | ExprKind::String(_) => {
+        }
+        ExprKind::Array(items) => items.iter().for_each(|e| walk_expr(visitor, e)),
+        ExprKind::Dictionary(items) => {
+            for (k, v) in items {
+                walk_expr(visitor, k);
+                walk_expr(visitor, v);
+            }
+        }
+        ExprKind::Call { arguments, .. } => walk_arguments(visitor, arguments),
+        ExprKind::MethodCall {
+            object, arguments, ..
+        } => {
+            walk_expr(visitor, object);
+            walk_arguments(visitor, arguments);
+        }
+        ExprKind::Index { object, index } => {
+            walk_expr(visitor, object);
+            walk_expr(visitor, index);
+        }
+        ExprKind::Unary { operand, .. } => walk_expr(visitor, operand),
+        ExprKind::Binary { left, right, .. } => {
+            walk_expr(visitor, left);
+            walk_expr(visitor, right);
+        }
+        ExprKind::Ternary {
+            condition,
+            if_true,
+            if_false,
+        } => {
+            walk_expr(visitor, condition);
+            walk_expr(visitor, if_true);
+            walk_expr(visitor, if_false);
+        }
+        ExprKind::Parenthesized(e) => walk_expr(visitor, e),
+    }
+}
+
+const KEYWORDS: &[&str] = &[
+    "if",
+    "elif",
+    "else",
+    "endif",
+    "foreach",
+    "endforeach",
+    "break",
+    "continue",
+    "and",
+    "or",
+    "not",
+    "in",
+    "true",
+    "false",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Identifier(String),
+    Number(i64),
+    String(StringLiteral),
+    Symbol(&'static str),
+    Newline,
+    Eof,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Token {
+    kind: TokenKind,
+    span: Span,
+}
+
+impl TokenKind {
+    fn describe(&self) -> String {
+        match self {
+            Self::Identifier(s) => format!("`{}`", s),
+            Self::Number(n) => format!("number `{}`", n),
+            Self::String(_) => "string".into(),
+            Self::Symbol(s) => format!("`{}`", s),
+            Self::Newline => "end of line".into(),
+            Self::Eof => "end of file".into(),
+        }
+    }
+}
+
+const SYMBOLS: &[&str] = &[
+    "+=", "==", "!=", "<=", ">=", "(", ")", "[", "]", "{", "}", ",", ":", ".", "?", "+", "-", "*",
+    "/", "%", "=", "<", ">",
+];
+
+struct Lexer<'a> {
+    text: &'a str,
+    position: Position,
+    /// depth of `( [ {`, newlines inside brackets are not statement ends
+    depth: usize,
+    line_depths: Vec<usize>,
+    comments: Vec<Span>,
+}
+impl<'a> Lexer<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            text,
+            position: Position {
+                // skip the byte order mark
+                offset: if text.starts_with('\u{feff}') { 3 } else { 0 },
+                line: 1,
+                column: 1,
+            },
+            depth: 0,
+            line_depths: vec![0],
+            comments: vec![],
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.text[self.position.offset..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.position.offset += c.len_utf8();
+        if c == '\n' {
+            self.line_depths.push(self.depth);
+            self.position.line += 1;
+            self.position.column = 1;
+        } else {
+            self.position.column += 1;
+        }
+        Some(c)
+    }
+
+    fn error(&self, found: impl Into<String>, expected: impl Into<String>) -> ParseError {
+        ParseError::UnexpectedSyntax {
+            found: found.into(),
+            expected: expected.into(),
+            line: self.position.line,
+            column: self.position.column,
+        }
+    }
+
+    fn tokenize(&mut self) -> Result<Vec<Token>> {
+        let mut tokens: Vec<Token> = vec![];
+        loop {
+            let start = self.position;
+            let c = match self.peek() {
+                None => {
+                    tokens.push(Token {
+                        kind: TokenKind::Eof,
+                        span: Span { start, end: start },
+                    });
+                    return Ok(tokens);
+                }
+                Some(c) => c,
+            };
+            let kind = match c {
+                '#' => {
+                    while !matches!(self.peek(), None | Some('\n')) {
+                        self.bump();
+                    }
+                    self.comments.push(Span {
+                        start,
+                        end: self.position,
+                    });
+                    continue;
+                }
+                '\n' => {
+                    self.bump();
+                    if self.depth > 0
+                        || matches!(
+                            tokens.last().map(|t| &t.kind),
+                            None | Some(TokenKind::Newline)
+                        )
+                    {
+                        continue;
+                    }
+                    TokenKind::Newline
+                }
+                c if c.is_whitespace() => {
+                    self.bump();
+                    continue;
+                }
+                '\'' => TokenKind::String(self.string(false)?),
+                'f' if self.rest().starts_with("f'") => {
+                    self.bump();
+                    TokenKind::String(self.string(true)?)
+                }
+                '"' => return Err(self.error("`\"`", "a string quoted with `'`")),
+                c if c.is_ascii_digit() => TokenKind::Number(self.number()?),
+                c if c.is_ascii_alphabetic() || c == '_' => {
+                    let mut name = String::new();
+                    while let Some(c) = self.peek().filter(|c| crate::grammar::is_identifier(*c)) {
+                        name.push(c);
+                        self.bump();
+                    }
+                    TokenKind::Identifier(name)
+                }
+                _ => {
+                    let symbol = SYMBOLS
+                        .iter()
+                        .find(|s| self.rest().starts_with(**s))
+                        .ok_or_else(|| self.error(format!("`{}`", c), "a meson token"))?;
+                    for _ in 0..symbol.len() {
+                        self.bump();
+                    }
+                    match *symbol {
+                        "(" | "[" | "{" => self.depth += 1,
+                        ")" | "]" | "}" => self.depth = self.depth.saturating_sub(1),
+                        _ => {}
+                    }
+                    TokenKind::Symbol(symbol)
+                }
+            };
+            tokens.push(Token {
+                kind,
+                span: Span {
+                    start,
+                    end: self.position,
+                },
+            });
+        }
+    }
+
+    fn number(&mut self) -> Result<i64> {
+        let start = self.position;
+        let mut literal = String::new();
+        while let Some(c) = self.peek().filter(|c| c.is_ascii_alphanumeric()) {
+            literal.push(c);
+            self.bump();
+        }
+        let lower = literal.to_ascii_lowercase();
+        let (digits, radix) = match lower.get(..2) {
+            Some("0x") => (&lower[2..], 16),
+            Some("0o") => (&lower[2..], 8),
+            Some("0b") => (&lower[2..], 2),
+            _ => (lower.as_str(), 10),
+        };
+        i64::from_str_radix(digits, radix).map_err(|_| ParseError::UnexpectedSyntax {
+            found: format!("`{}`", literal),
+            expected: "an integer literal".into(),
+            line: start.line,
+            column: start.column,
+        })
+    }
+
+    fn string(&mut self, format: bool) -> Result<StringLiteral> {
+        let start = self.position;
+        let multiline = self.rest().starts_with("'''");
+        let delimiter = if multiline { "'''" } else { "'" };
+        for _ in 0..delimiter.len() {
+            self.bump();
+        }
+        let unclosed = || ParseError::UnexpectedSyntax {
+            found: "unterminated string".into(),
+            expected: format!("closing `{}`", delimiter),
+            line: start.line,
+            column: start.column,
+        };
+        let mut value = String::new();
+        loop {
+            if self.rest().starts_with(delimiter) {
+                for _ in 0..delimiter.len() {
+                    self.bump();
+                }
+                return Ok(StringLiteral {
+                    value,
+                    format,
+                    multiline,
+                });
+            }
+            match self.bump() {
+                None => return Err(unclosed()),
+                Some('\n') if !multiline => return Err(unclosed()),
+                // multiline strings keep escapes as written
+                Some('\\') if !multiline => match self.bump().ok_or_else(unclosed)? {
+                    'n' => value.push('\n'),
+                    't' => value.push('\t'),
+                    'r' => value.push('\r'),
+                    c @ ('\\' | '\'') => value.push(c),
+                    c => {
+                        value.push('\\');
+                        value.push(c);
+                    }
+                },
+                Some(c) => value.push(c),
+            }
+        }
+    }
+}
+
+struct TreeParser {
+    tokens: Vec<Token>,
+    index: usize,
+}
+impl TreeParser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.index]
+    }
+
+    fn bump(&mut self) -> Token {
+        let token = self.tokens[self.index].clone();
+        if self.index + 1 < self.tokens.len() {
+            self.index += 1;
+        }
+        token
+    }
+
+    fn at_symbol(&self, symbol: &str) -> bool {
+        matches!(&self.peek().kind, TokenKind::Symbol(s) if *s == symbol)
+    }
+
+    fn at_keyword(&self, keyword: &str) -> bool {
+        matches!(&self.peek().kind, TokenKind::Identifier(s) if s == keyword)
+    }
+
+    fn eat_symbol(&mut self, symbol: &str) -> bool {
+        let found = self.at_symbol(symbol);
+        if found {
+            self.bump();
+        }
+        found
+    }
+
+    fn error(&self, expected: impl Into<String>) -> ParseError {
+        let token = self.peek();
+        ParseError::UnexpectedSyntax {
+            found: token.kind.describe(),
+            expected: expected.into(),
+            line: token.span.start.line,
+            column: token.span.start.column,
+        }
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<Span> {
+        if self.at_symbol(symbol) {
+            Ok(self.bump().span)
+        } else {
+            Err(self.error(format!("`{}`", symbol)))
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<Span> {
+        if self.at_keyword(keyword) {
+            Ok(self.bump().span)
+        } else {
+            Err(self.error(format!("`{}`", keyword)))
+        }
+    }
+
+    fn expect_identifier(&mut self) -> Result<Ident> {
+        match &self.peek().kind {
+            TokenKind::Identifier(name) if !KEYWORDS.contains(&name.as_str()) => {
+                let name = name.clone();
+                let span = self.bump().span;
+                Ok(Ident { name, span })
+            }
+            _ => Err(self.error("an identifier")),
+        }
+    }
+
+    fn end_of_statement(&mut self) -> Result<()> {
+        match self.peek().kind {
+            TokenKind::Newline => {
+                self.bump();
+                Ok(())
+            }
+            TokenKind::Eof => Ok(()),
+            _ => Err(self.error("end of line")),
+        }
+    }
+
+    fn parse_file(mut self) -> Result<File> {
+        if self.peek().kind == TokenKind::Newline {
+            self.bump();
+        }
+        let statements = self.parse_block(&[])?;
+        if self.peek().kind != TokenKind::Eof {
+            return Err(self.error("a statement"));
+        }
+        Ok(File { statements })
+    }
+
+    /// parse statements until one of `terminators` keywords
+    fn parse_block(&mut self, terminators: &[&str]) -> Result<Vec<Statement>> {
+        let mut statements = vec![];
+        loop {
+            match &self.peek().kind {
+                TokenKind::Eof => return Ok(statements),
+                TokenKind::Newline => {
+                    self.bump();
+                }
+                TokenKind::Identifier(s) if terminators.contains(&s.as_str()) => {
+                    return Ok(statements)
+                }
+                _ => statements.push(self.parse_statement()?),
+            }
+        }
+    }
+
+    fn parse_statement(&mut self) -> Result<Statement> {
+        let start = self.peek().span;
+        let keyword = match &self.peek().kind {
+            TokenKind::Identifier(s) => s.clone(),
+            _ => String::new(),
+        };
+        let statement = match keyword.as_str() {
+            "if" => {
+                self.bump();
+                let mut branches = vec![];
+                let mut else_block = None;
+                loop {
+                    let condition = self.parse_expr()?;
+                    self.end_of_statement()?;
+                    let block = self.parse_block(&["elif", "else", "endif"])?;
+                    branches.push((condition, block));
+                    if self.at_keyword("elif") {
+                        self.bump();
+                        continue;
+                    }
+                    if self.at_keyword("else") {
+                        self.bump();
+                        self.end_of_statement()?;
+                        else_block = Some(self.parse_block(&["endif"])?);
+                    }
+                    break;
+                }
+                let end = self.expect_keyword("endif")?;
+                Statement::If {
+                    branches,
+                    else_block,
+                    span: start.to(end),
+                }
+            }
+            "foreach" => {
+                self.bump();
+                let mut variables = vec![self.expect_identifier()?];
+                while self.eat_symbol(",") {
+                    variables.push(self.expect_identifier()?);
+                }
+                self.expect_symbol(":")?;
+                let items = self.parse_expr()?;
+                self.end_of_statement()?;
+                let body = self.parse_block(&["endforeach"])?;
+                let end = self.expect_keyword("endforeach")?;
+                Statement::Foreach {
+                    variables,
+                    items,
+                    body,
+                    span: start.to(end),
+                }
+            }
+            "break" => Statement::Break(self.bump().span),
+            "continue" => Statement::Continue(self.bump().span),
+            "elif" | "else" | "endif" | "endforeach" => return Err(self.error("a statement")),
+            _ => {
+                let expr = self.parse_expr()?;
+                let operator = if self.at_symbol("=") {
+                    Some(AssignOperator::Assign)
+                } else if self.at_symbol("+=") {
+                    Some(AssignOperator::AddAssign)
+                } else {
+                    None
+                };
+                match operator {
+                    None => Statement::Expression(expr),
+                    Some(operator) => {
+                        let target = match expr.kind {
+                            ExprKind::Identifier(name) => Ident {
+                                name,
+                                span: expr.span,
+                            },
+                            _ => return Err(self.error("an identifier before assignment")),
+                        };
+                        self.bump();
+                        let value = self.parse_expr()?;
+                        Statement::Assignment {
+                            span: target.span.to(value.span),
+                            target,
+                            operator,
+                            value,
+                        }
+                    }
+                }
+            }
+        };
+        self.end_of_statement()?;
+        Ok(statement)
+    }
+
+    fn parse_expr(&mut self) -> Result<Expr> {
+        let condition = self.parse_binary(0)?;
+        if !self.eat_symbol("?") {
+            return Ok(condition);
+        }
+        let if_true = self.parse_expr()?;
+        self.expect_symbol(":")?;
+        let if_false = self.parse_expr()?;
+        Ok(Expr {
+            span: condition.span.to(if_false.span),
+            kind: ExprKind::Ternary {
+                condition: Box::new(condition),
+                if_true: Box::new(if_true),
+                if_false: Box::new(if_false),
+            },
+        })
+    }
+
+    /// binary operator at current token
+    fn peek_binary_operator(&self) -> Option<BinaryOperator> {
+        let operator = match &self.peek().kind {
+            TokenKind::Identifier(s) => match s.as_str() {
+                "or" => BinaryOperator::Or,
+                "and" => BinaryOperator::And,
+                "in" => BinaryOperator::In,
+                "not" => match self.tokens.get(self.index + 1).map(|t| &t.kind) {
+                    Some(TokenKind::Identifier(s)) if s == "in" => BinaryOperator::NotIn,
+                    _ => return None,
+                },
+                _ => return None,
+            },
+            TokenKind::Symbol(s) => match *s {
+                "==" => BinaryOperator::Equal,
+                "!=" => BinaryOperator::NotEqual,
+                "<" => BinaryOperator::Less,
+                "<=" => BinaryOperator::LessEqual,
+                ">" => BinaryOperator::Greater,
+                ">=" => BinaryOperator::GreaterEqual,
+                "+" => BinaryOperator::Add,
+                "-" => BinaryOperator::Subtract,
+                "*" => BinaryOperator::Multiply,
+                "/" => BinaryOperator::Divide,
+                "%" => BinaryOperator::Modulo,
+                _ => return None,
+            },
+            _ => return None,
+        };
+        Some(operator)
+    }
+
+    fn parse_binary(&mut self, min_level: usize) -> Result<Expr> {
+        let mut left = self.parse_unary()?;
+        while let Some(operator) = self.peek_binary_operator() {
+            let level = operator.precedence();
+            if level < min_level {
+                break;
+            }
+            self.bump();
+            if operator == BinaryOperator::NotIn {
+                self.bump();
+            }
+            let right = self.parse_binary(level + 1)?;
+            left = Expr {
+                span: left.span.to(right.span),
+                kind: ExprKind::Binary {
+                    operator,
+                    left: Box::new(left),
+                    right: Box::new(right),
+                },
+            };
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr> {
+        let operator = if self.at_keyword("not") {
+            UnaryOperator::Not
+        } else if self.at_symbol("-") {
+            UnaryOperator::Negative
+        } else {
+            return self.parse_postfix();
+        };
+        let start = self.bump().span;
+        let operand = self.parse_unary()?;
+        Ok(Expr {
+            span: start.to(operand.span),
+            kind: ExprKind::Unary {
+                operator,
+                operand: Box::new(operand),
+            },
+        })
+    }
+
+    fn parse_postfix(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_primary()?;
+        loop {
+            if self.eat_symbol(".") {
+                let name = self.expect_identifier()?;
+                self.expect_symbol("(")?;
+                let arguments = self.parse_arguments()?;
+                expr = Expr {
+                    span: expr.span.to(arguments.span),
+                    kind: ExprKind::MethodCall {
+                        object: Box::new(expr),
+                        name,
+                        arguments,
+                    },
+                };
+            } else if self.eat_symbol("[") {
+                let index = self.parse_expr()?;
+                let end = self.expect_symbol("]")?;
+                expr = Expr {
+                    span: expr.span.to(end),
+                    kind: ExprKind::Index {
+                        object: Box::new(expr),
+                        index: Box::new(index),
+                    },
+                };
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr> {
+        let token = self.peek().clone();
+        let kind = match token.kind {
+            TokenKind::Number(n) => {
+                self.bump();
+                ExprKind::Number(n)
+            }
+            TokenKind::String(s) => {
+                self.bump();
+                ExprKind::String(s)
+            }
+            TokenKind::Identifier(name) if name == "true" || name == "false" => {
+                self.bump();
+                ExprKind::Bool(name == "true")
+            }
+            TokenKind::Identifier(_) => {
+                let name = self.expect_identifier()?;
+                if !self.eat_symbol("(") {
+                    return Ok(Expr {
+                        span: name.span,
+                        kind: ExprKind::Identifier(name.name),
+                    });
+                }
+                let arguments = self.parse_arguments()?;
+                return Ok(Expr {
+                    span: name.span.to(arguments.span),
+                    kind: ExprKind::Call { name, arguments },
+                });
+            }
+            TokenKind::Symbol("(") => {
+                self.bump();
+                let inner = self.parse_expr()?;
+                let end = self.expect_symbol(")")?;
+                return Ok(Expr {
+                    span: token.span.to(end),
+                    kind: ExprKind::Parenthesized(Box::new(inner)),
+                });
+            }
+            TokenKind::Symbol("[") => {
+                self.bump();
+                let mut items = vec![];
+                while !self.at_symbol("]") {
+                    items.push(self.parse_expr()?);
+                    if !self.eat_symbol(",") {
+                        break;
+                    }
+                }
+                let end = self.expect_symbol("]")?;
+                return Ok(Expr {
+                    span: token.span.to(end),
+                    kind: ExprKind::Array(items),
+                });
+            }
+            TokenKind::Symbol("{") => {
+                self.bump();
+                let mut items = vec![];
+                while !self.at_symbol("}") {
+                    let key = self.parse_expr()?;
+                    self.expect_symbol(":")?;
+                    items.push((key, self.parse_expr()?));
+                    if !self.eat_symbol(",") {
+                        break;
+                    }
+                }
+                let end = self.expect_symbol("}")?;
+                return Ok(Expr {
+                    span: token.span.to(end),
+                    kind: ExprKind::Dictionary(items),
+                });
+            }
+            _ => return Err(self.error("an expression")),
+        };
+        Ok(Expr {
+            kind,
+            span: token.span,
+        })
+    }
+
+    /// parse after `(` until `)`, the span covers both brackets
+    fn parse_arguments(&mut self) -> Result<Arguments> {
+        let start = self.tokens[self.index - 1].span;
+        let mut arguments = Arguments::default();
+        while !self.at_symbol(")") {
+            let expr = self.parse_expr()?;
+            if self.eat_symbol(":") {
+                let name = match expr.kind {
+                    ExprKind::Identifier(name) => Ident {
+                        name,
+                        span: expr.span,
+                    },
+                    _ => return Err(self.error("an identifier before `:`")),
+                };
+                let value = self.parse_expr()?;
+                arguments.keywords.push(KeywordArgument { name, value });
+            } else if !arguments.keywords.is_empty() {
+                return Err(ParseError::UnexpectedSyntax {
+                    found: "positional argument".into(),
+                    expected: "keyword arguments only after a keyword argument".into(),
+                    line: expr.span.start.line,
+                    column: expr.span.start.column,
+                });
+            } else {
+                arguments.positional.push(expr);
+            }
+            if !self.eat_symbol(",") {
+                break;
+            }
+        }
+        let end = self.expect_symbol(")")?;
+        arguments.span = start.to(end);
+        Ok(arguments)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_expr(text: &str) -> Expr {
+        match parse_file(text).unwrap().statements.remove(0) {
+            Statement::Expression(e) => e,
+            s => panic!("not expression: {:?}", s),
+        }
+    }
+
+    #[test]
+    fn parse_precedence() {
+        let expr = parse_expr("a or b and not c == 1 + 2 * 3");
+        let ExprKind::Binary {
+            operator, right, ..
+        } = expr.kind
+        else {
+            panic!()
+        };
+        assert_eq!(operator, BinaryOperator::Or);
+        let ExprKind::Binary { operator, .. } = right.kind else {
+            panic!()
+        };
+        assert_eq!(operator, BinaryOperator::And);
+
+        let expr = parse_expr("x not in [1, 2] ? -1 : 0x1F");
+        let ExprKind::Ternary {
+            condition,
+            if_true,
+            if_false,
+        } = expr.kind
+        else {
+            panic!()
+        };
+        assert!(matches!(
+            condition.kind,
+            ExprKind::Binary {
+                operator: BinaryOperator::NotIn,
+                ..
+            }
+        ));
+        assert!(matches!(if_true.kind, ExprKind::Unary { .. }));
+        assert_eq!(if_false.kind, ExprKind::Number(31));
+    }
+
+    #[test]
+    fn parse_calls() {
+        let expr = parse_expr("meson.get_compiler('c').get_id()");
+        let ExprKind::MethodCall { object, name, .. } = expr.kind else {
+            panic!()
+        };
+        assert_eq!(name.name, "get_id");
+        assert!(matches!(object.kind, ExprKind::MethodCall { .. }));
+
+        let expr = parse_expr("executable('a',\n  'a.c', # comment\n  install: true,\n)");
+        let ExprKind::Call { name, arguments } = expr.kind else {
+            panic!()
+        };
+        assert_eq!(name.name, "executable");
+        assert_eq!(arguments.positional.len(), 2);
+        assert_eq!(arguments.keywords[0].name.name, "install");
+        assert_eq!(arguments.keywords[0].name.span.start.line, 3);
+        assert_eq!(arguments.keywords[0].name.span.start.column, 3);
+        assert_eq!(
+            arguments.keyword("install").unwrap().kind,
+            ExprKind::Bool(true)
+        );
+    }
+
+    #[test]
+    fn parse_statements() {
+        let file = parse_file(
+            "a = 1\nif a == 1\n  b += [a]\nelif a\nelse\n  c = 'x'\nendif\nforeach k, v : {'a': 1}\n  break\nendforeach\n",
+        )
+        .unwrap();
+        assert_eq!(file.statements.len(), 3);
+        assert!(
+            matches!(&file.statements[1], Statement::If { branches, else_block: Some(_), .. } if branches.len() == 2)
+        );
+        assert!(
+            matches!(&file.statements[2], Statement::Foreach { variables, body, .. } if variables.len() == 2 && body == &[Statement::Break(body[0].span())])
+        );
+    }
+
+    #[test]
+    fn parse_strings() {
+        let expr = parse_expr("f'''a\n@b@\\n'''");
+        assert_eq!(
+            expr.kind,
+            ExprKind::String(StringLiteral {
+                value: "a\n@b@\\n".into(),
+                format: true,
+                multiline: true,
+            })
+        );
+        assert_eq!(parse_expr("'it\\'s'").kind.as_str(), Some("it's"));
+    }
+
+    #[test]
+    fn bracket_depths() {
+        let text = "x = f(a,\n  [b,\n  c]) # (\ny = '''\n'''\n";
+        assert_eq!(line_depths(text).unwrap(), [0, 1, 2, 0, 0, 0]);
+    }
+
+    #[test]
+    fn parse_errors() {
+        for (text, line, column) in [
+            ("a = 1 +\n2", 1, 8),
+            ("a = 'b", 1, 5),
+            ("if a\n", 2, 1),
+            ("f(a: 1, 2)", 1, 9),
+            ("a = $", 1, 5),
+            ("a = 0x", 1, 5),
+            ("a = [\n  \"b\"]", 2, 3),
+            // meson has no unary plus
+            ("a = +1", 1, 5),
+            ("a = b\n+ c", 2, 1),
+        ] {
+            match parse_file(text) {
+                Err(ParseError::UnexpectedSyntax {
+                    line: l, column: c, ..
+                }) => assert_eq!((l, c), (line, column), "{}", text),
+                r => panic!("{}: {:?}", text, r),
+            }
+        }
+    }
+}
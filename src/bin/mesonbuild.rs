@@ -0,0 +1,39 @@
+use anyhow::{bail, Result};
+use dprint_plugin_mesonbuild::{discover_project, project_inventory};
+use std::{env, path::PathBuf};
+
+const USAGE: &str = "usage: mesonbuild inventory [--json | --table] [ROOT]";
+
+fn main() -> Result<()> {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    match args.split_first() {
+        Some((command, args)) if command == "inventory" => inventory(args),
+        _ => bail!(USAGE),
+    }
+}
+
+/// print the inventory of the project at `ROOT`, the current directory by
+/// default, as a table or as JSON
+fn inventory(args: &[String]) -> Result<()> {
+    let mut json = false;
+    let mut root = None;
+    for arg in args {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--table" => json = false,
+            _ if arg.starts_with('-') || root.is_some() => bail!(USAGE),
+            _ => root = Some(PathBuf::from(arg)),
+        }
+    }
+    let root = root.unwrap_or_else(|| PathBuf::from("."));
+    if !root.join("meson.build").is_file() {
+        bail!("no meson.build in {}", root.display());
+    }
+    let inventory = project_inventory(&discover_project(&root));
+    if json {
+        println!("{}", inventory.to_json());
+    } else {
+        print!("{}", inventory.to_table());
+    }
+    Ok(())
+}
@@ -0,0 +1,595 @@
+use crate::ast::{self, Arguments, Expr, ExprKind, File, Span, Statement, Visitor};
+use crate::parser::ParseError;
+use crate::signatures::{database, Database, Signature};
+use crate::version::{Version, VersionRequirement};
+use std::{collections::HashMap, fmt, path::Path};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub span: Span,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(
+            f,
+            "{}:{}: {}: {}",
+            self.span.start.line, self.span.start.column, severity, self.message
+        )
+    }
+}
+
+/// check calls in a file against the bundled signature database
+pub fn check_text(file_text: &str) -> Result<Vec<Diagnostic>, ParseError> {
+    let file = ast::parse_file(file_text)?;
+    let meson_version = project_meson_version(&file);
+    Ok(check_file(&file, meson_version.as_ref()))
+}
+
+/// `meson_version` has to be given for files reached by `subdir()`,
+/// it is only declared in the top level `project()`
+pub fn check_file(file: &File, meson_version: Option<&VersionRequirement>) -> Vec<Diagnostic> {
+    let mut checker = Checker {
+        database: database(),
+        meson_version: meson_version.and_then(VersionRequirement::minimum),
+        types: Types::new(),
+        diagnostics: vec![],
+    };
+    ast::walk_statements(&mut checker, &file.statements);
+    checker.diagnostics
+}
+
+/// like `check_file`, also checks the file name is supported by `meson_version`
+pub fn check_path(
+    path: &Path,
+    file: &File,
+    meson_version: Option<&VersionRequirement>,
+) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    let minimum = meson_version.and_then(VersionRequirement::minimum);
+    if let Some(minimum) = minimum.filter(|v| *v < Version(vec![1, 1])) {
+        if path.file_name().and_then(|n| n.to_str()) == Some("meson.options") {
+            diagnostics.push(Diagnostic {
+                severity: Severity::Warning,
+                message: format!(
+                    "`meson.options` was added in meson 1.1, but the project requires meson >= {}, use `meson_options.txt` instead",
+                    minimum
+                ),
+                span: Span::default(),
+            });
+        }
+    }
+    diagnostics.extend(check_file(file, meson_version));
+    diagnostics
+}
+
+/// constraints of `project(meson_version: ...)`
+pub fn project_meson_version(file: &File) -> Option<VersionRequirement> {
+    file.statements
+        .iter()
+        .find_map(|statement| match statement {
+            Statement::Expression(Expr {
+                kind: ExprKind::Call { name, arguments },
+                ..
+            }) if name.name == "project" => match &arguments.keyword("meson_version")?.kind {
+                ExprKind::String(s) => s.value.parse().ok(),
+                ExprKind::Array(items) => {
+                    let mut constraints = vec![];
+                    for item in items {
+                        let requirement = item.kind.as_str()?.parse::<VersionRequirement>();
+                        constraints.extend(requirement.ok()?.0);
+                    }
+                    Some(VersionRequirement(constraints))
+                }
+                _ => None,
+            },
+            _ => None,
+        })
+}
+
+struct Checker<'a> {
+    database: &'a Database,
+    /// lowest version allowed by `project(meson_version: ...)`
+    meson_version: Option<Version>,
+    types: Types,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Visitor for Checker<'a> {
+    fn visit_statement(&mut self, statement: &Statement) -> bool {
+        self.types.enter(statement);
+        match statement {
+            Statement::Break(span) => self.check_feature("`break`", "0.49.0", *span),
+            Statement::Continue(span) => self.check_feature("`continue`", "0.49.0", *span),
+            _ => {}
+        }
+        true
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) -> bool {
+        match &expr.kind {
+            ExprKind::String(s) if s.format && s.multiline => {
+                self.check_feature("multiline format string", "0.63.0", expr.span)
+            }
+            ExprKind::String(s) if s.format => {
+                self.check_feature("format string", "0.58.0", expr.span)
+            }
+            ExprKind::Ternary { .. } => self.check_feature("ternary operator", "0.43.0", expr.span),
+            ExprKind::Dictionary(_) => self.check_feature("dictionary", "0.47.0", expr.span),
+            ExprKind::Binary {
+                operator: ast::BinaryOperator::Divide,
+                left,
+                ..
+            } if self.types.type_of(left).as_deref() == Some("str") => {
+                self.check_feature("path join operator `/`", "0.49.0", expr.span)
+            }
+            ExprKind::Call { name, arguments } => match self.database.functions.get(&name.name) {
+                Some(signature) => {
+                    self.check_call(&format!("{}()", name.name), signature, arguments, name.span)
+                }
+                None => {
+                    let candidates = self.database.functions.keys().map(String::as_str);
+                    self.error(
+                        with_suggestion(
+                            format!("unknown function `{}`", name.name),
+                            &name.name,
+                            candidates,
+                        ),
+                        name.span,
+                    );
+                }
+            },
+            ExprKind::MethodCall {
+                object,
+                name,
+                arguments,
+            } => {
+                let object_type = self.types.type_of(object);
+                let methods = object_type
+                    .as_ref()
+                    .and_then(|t| Some((t, self.database.objects.get(t)?)));
+                if let Some((object_type, methods)) = methods {
+                    match methods.get(&name.name) {
+                        Some(signature) => self.check_call(
+                            &format!("{}.{}()", object_type, name.name),
+                            signature,
+                            arguments,
+                            name.span,
+                        ),
+                        None => self.error(
+                            with_suggestion(
+                                format!("unknown method `{}` of `{}`", name.name, object_type),
+                                &name.name,
+                                methods.keys().map(String::as_str),
+                            ),
+                            name.span,
+                        ),
+                    }
+                }
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn leave_statement(&mut self, statement: &Statement) {
+        self.types.leave(statement);
+    }
+}
+
+impl<'a> Checker<'a> {
+    fn error(&mut self, message: String, span: Span) {
+        self.diagnostics.push(Diagnostic {
+            severity: Severity::Error,
+            message,
+            span,
+        });
+    }
+
+    fn warning(&mut self, message: String, span: Span) {
+        self.diagnostics.push(Diagnostic {
+            severity: Severity::Warning,
+            message,
+            span,
+        });
+    }
+
+    fn check_feature(&mut self, what: &str, since: &str, span: Span) {
+        self.check_version(what, since.parse().ok(), None, span);
+    }
+
+    /// warn about features newer than `meson_version` or deprecated by it
+    fn check_version(
+        &mut self,
+        what: &str,
+        since: Option<Version>,
+        deprecated: Option<(Version, Option<&str>)>,
+        span: Span,
+    ) {
+        let meson_version = match &self.meson_version {
+            Some(v) => v.clone(),
+            None => return,
+        };
+        if let Some(since) = since.filter(|since| *since > meson_version) {
+            self.warning(
+                format!(
+                    "{} was added in meson {}, but the project requires meson >= {}",
+                    what, since, meson_version
+                ),
+                span,
+            );
+        }
+        if let Some((deprecated, replacement)) = deprecated.filter(|(d, _)| *d <= meson_version) {
+            let mut message = format!("{} is deprecated since meson {}", what, deprecated);
+            if let Some(replacement) = replacement {
+                message.push_str(&format!(", use `{}` instead", replacement));
+            }
+            self.warning(message, span);
+        }
+    }
+
+    fn check_call(
+        &mut self,
+        callee: &str,
+        signature: &Signature,
+        arguments: &Arguments,
+        span: Span,
+    ) {
+        let database = self.database;
+
+        self.check_version(
+            &format!("`{}`", callee),
+            signature.since(),
+            signature
+                .deprecated()
+                .map(|d| (d, signature.replacement.as_deref())),
+            span,
+        );
+
+        let count = arguments.positional.len();
+        match signature.args {
+            (min, _) if count < min => self.error(
+                format!(
+                    "`{}` requires at least {} positional argument{}, found {}",
+                    callee,
+                    min,
+                    if min == 1 { "" } else { "s" },
+                    count
+                ),
+                span,
+            ),
+            (_, Some(max)) if count > max => self.error(
+                format!(
+                    "`{}` accepts at most {} positional argument{}, found {}",
+                    callee,
+                    max,
+                    if max == 1 { "" } else { "s" },
+                    count
+                ),
+                arguments.positional[max].span,
+            ),
+            _ => {}
+        }
+
+        // `kwargs: dict` may carry any keyword argument
+        let has_kwargs_dict = arguments.keyword("kwargs").is_some();
+        for keyword in &arguments.keywords {
+            let name = &keyword.name.name;
+            if name == "kwargs" {
+                continue;
+            }
+            let spec = match signature.kwarg(database, name) {
+                Some(spec) => spec,
+                None => {
+                    let kwargs = signature.kwargs(database);
+                    let candidates = kwargs
+                        .iter()
+                        .map(|(k, _)| *k)
+                        .filter(|k| !k.starts_with('*'));
+                    let message = with_suggestion(
+                        format!("unknown keyword argument `{}` for `{}`", name, callee),
+                        name,
+                        candidates,
+                    );
+                    self.error(message, keyword.name.span);
+                    continue;
+                }
+            };
+            self.check_version(
+                &format!("keyword argument `{}` of `{}`", name, callee),
+                spec.since(),
+                spec.deprecated(),
+                keyword.name.span,
+            );
+            if let Some(found) = literal_type(&keyword.value) {
+                if !spec.types().any(|t| t == found || t == "any") {
+                    let expected = spec.types().collect::<Vec<_>>().join(" | ");
+                    self.error(
+                        format!(
+                            "keyword argument `{}` of `{}` expects {}, found {}",
+                            name, callee, expected, found
+                        ),
+                        keyword.value.span,
+                    );
+                }
+            }
+        }
+
+        if !has_kwargs_dict {
+            for (name, spec) in signature.kwargs(database) {
+                if spec.required() && arguments.keyword(name).is_none() {
+                    self.error(
+                        format!("`{}` requires keyword argument `{}`", callee, name),
+                        span,
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Object types of variables, following the assignments in the order meson
+/// runs them. Statements are passed to `enter` and `leave` while walking the
+/// file.
+pub(crate) struct Types {
+    database: &'static Database,
+    /// object type of variables, `None` when unknown
+    variables: HashMap<String, Option<String>>,
+}
+
+impl Types {
+    pub(crate) fn new() -> Self {
+        Self {
+            database: database(),
+            variables: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn enter(&mut self, statement: &Statement) {
+        if let Statement::Foreach { variables, .. } = statement {
+            for variable in variables {
+                self.variables.insert(variable.name.clone(), None);
+            }
+        }
+    }
+
+    pub(crate) fn leave(&mut self, statement: &Statement) {
+        if let Statement::Assignment {
+            target,
+            operator,
+            value,
+            ..
+        } = statement
+        {
+            let value_type = self.type_of(value);
+            let value_type = match (operator, self.variables.get(&target.name)) {
+                (ast::AssignOperator::AddAssign, Some(old)) if *old != value_type => {
+                    old.clone().filter(|t| t == "list")
+                }
+                _ => value_type,
+            };
+            self.variables.insert(target.name.clone(), value_type);
+        }
+    }
+
+    /// object type of an expression if it can be told statically
+    pub(crate) fn type_of(&self, expr: &Expr) -> Option<String> {
+        let literal = literal_type(expr).map(String::from);
+        match &expr.kind {
+            ExprKind::Identifier(name) => match self.variables.get(name) {
+                Some(t) => t.clone(),
+                None => self.database.variables.get(name).cloned(),
+            },
+            ExprKind::Call { name, .. } => self.database.functions.get(&name.name)?.returns.clone(),
+            ExprKind::MethodCall { object, name, .. } => {
+                let object_type = self.type_of(object)?;
+                let methods = self.database.objects.get(&object_type)?;
+                methods.get(&name.name)?.returns.clone()
+            }
+            ExprKind::Binary { operator, left, .. } => {
+                match (operator, self.type_of(left)?.as_str()) {
+                    (ast::BinaryOperator::Add, "list") => Some("list".into()),
+                    (ast::BinaryOperator::Add, "str") | (ast::BinaryOperator::Divide, "str") => {
+                        Some("str".into())
+                    }
+                    _ => None,
+                }
+            }
+            ExprKind::Parenthesized(e) => self.type_of(e),
+            _ => literal,
+        }
+    }
+}
+
+/// type name of a literal value
+fn literal_type(expr: &Expr) -> Option<&'static str> {
+    match expr.kind {
+        ExprKind::String(_) => Some("str"),
+        ExprKind::Bool(_) => Some("bool"),
+        ExprKind::Number(_) => Some("int"),
+        ExprKind::Array(_) => Some("list"),
+        ExprKind::Dictionary(_) => Some("dict"),
+        _ => None,
+    }
+}
+
+fn with_suggestion<'a>(
+    message: String,
+    name: &str,
+    candidates: impl Iterator<Item = &'a str>,
+) -> String {
+    match suggest(name, candidates) {
+        Some(s) => format!("{}, did you mean `{}`?", message, s),
+        None => message,
+    }
+}
+
+/// the closest candidate within a small edit distance
+pub(crate) fn suggest<'a>(
+    name: &str,
+    candidates: impl Iterator<Item = &'a str>,
+) -> Option<&'a str> {
+    let limit = (name.chars().count() / 3).max(1);
+    candidates
+        .map(|c| (edit_distance(name, c), c))
+        .filter(|(d, _)| *d <= limit)
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, c)| c)
+}
+
+/// Damerau-Levenshtein distance, swapped neighbours count as one edit
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    d[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(text: &str) -> Vec<String> {
+        check_text(text)
+            .unwrap()
+            .into_iter()
+            .map(|d| d.message)
+            .collect()
+    }
+
+    #[test]
+    fn check_unknown_kwargs() {
+        assert_eq!(
+            messages("executable('a', 'a.c', link_wiht: lib, instal: true, cpp_args: [])"),
+            vec![
+                "unknown keyword argument `link_wiht` for `executable()`, did you mean `link_with`?",
+                "unknown keyword argument `instal` for `executable()`, did you mean `install`?",
+            ]
+        );
+        assert!(messages("custom_target('a', kwargs: d, input: 'a.in')").is_empty());
+        assert_eq!(
+            messages("executable('a', install: 'yes')"),
+            vec!["keyword argument `install` of `executable()` expects bool, found str"]
+        );
+    }
+
+    #[test]
+    fn check_arguments() {
+        assert_eq!(
+            messages("executable()\nsubdir('a', 'b')\ncustom_target('x')\nexectuable('a')"),
+            vec![
+                "`executable()` requires at least 1 positional argument, found 0",
+                "`subdir()` accepts at most 1 positional argument, found 2",
+                "`custom_target()` requires keyword argument `output`",
+                "unknown function `exectuable`, did you mean `executable`?",
+            ]
+        );
+    }
+
+    #[test]
+    fn check_methods() {
+        assert_eq!(
+            messages("cc = meson.get_compiler('c')\ncc.has_arguments('-Wall')\n'a'.to_uper()\nx.anything()"),
+            vec![
+                "unknown method `has_arguments` of `compiler`, did you mean `has_argument`?",
+                "unknown method `to_uper` of `str`, did you mean `to_upper`?",
+            ]
+        );
+    }
+
+    #[test]
+    fn check_versions() {
+        assert_eq!(
+            messages(
+                "project('a', meson_version: '>=0.47.0')\nexecutable('a', pie: true)\nmeson.source_root()"
+            ),
+            vec![
+                "keyword argument `pie` of `executable()` was added in meson 0.49.0, but the project requires meson >= 0.47.0",
+            ]
+        );
+        assert_eq!(
+            messages("project('a', meson_version: '>=0.56.0')\nsrc = meson.source_root()\nd = dependency('x', version: '>=1')\nd.get_pkgconfig_variable('p')"),
+            vec![
+                "`meson.source_root()` is deprecated since meson 0.56.0, use `meson.project_source_root() or meson.global_source_root()` instead",
+                "`dep.get_pkgconfig_variable()` is deprecated since meson 0.56.0, use `dep.get_variable(pkgconfig: ...)` instead",
+            ]
+        );
+        assert!(messages("executable('a', pie: true)").is_empty());
+    }
+
+    #[test]
+    fn check_features() {
+        let text = "project('a', meson_version: ['>=0.42', '<1.0'])
+foreach x : [1]
+  if x == 1
+    continue
+  endif
+endforeach
+a = x ? f'@x@' : f'''
+@x@'''
+b = {'k': 'a' / 'b'}
+";
+        assert_eq!(
+            messages(text),
+            vec![
+                "`continue` was added in meson 0.49.0, but the project requires meson >= 0.42",
+                "ternary operator was added in meson 0.43.0, but the project requires meson >= 0.42",
+                "format string was added in meson 0.58.0, but the project requires meson >= 0.42",
+                "multiline format string was added in meson 0.63.0, but the project requires meson >= 0.42",
+                "dictionary was added in meson 0.47.0, but the project requires meson >= 0.42",
+                "path join operator `/` was added in meson 0.49.0, but the project requires meson >= 0.42",
+            ]
+        );
+        let file = ast::parse_file(text).unwrap();
+        assert_eq!(
+            project_meson_version(&file),
+            Some(">=0.42, <1.0".parse().unwrap())
+        );
+        assert!(check_text(&text.replace("0.42", "0.63"))
+            .unwrap()
+            .is_empty());
+
+        let requirement = "<1.3, >=1.0".parse().ok();
+        let file = ast::parse_file("option('a', type: 'boolean', value: true)").unwrap();
+        assert_eq!(
+            check_path(Path::new("meson.options"), &file, requirement.as_ref())[0].message,
+            "`meson.options` was added in meson 1.1, but the project requires meson >= 1.0, use `meson_options.txt` instead"
+        );
+        assert!(check_path(Path::new("meson_options.txt"), &file, requirement.as_ref()).is_empty());
+    }
+
+    #[test]
+    fn distance() {
+        assert_eq!(edit_distance("link_wiht", "link_with"), 1);
+        assert_eq!(edit_distance("instal", "install"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(suggest("zzzz", ["install"].iter().copied()), None);
+    }
+}
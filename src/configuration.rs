@@ -1,12 +1,13 @@
 use dprint_core::configuration::get_unknown_property_diagnostics;
 use dprint_core::configuration::{
-    ConfigKeyMap, ConfigurationDiagnostic, GlobalConfiguration, ResolveConfigurationResult,
+    ConfigKeyMap, ConfigKeyValue, ConfigurationDiagnostic, GlobalConfiguration, NewLineKind,
+    ParseConfigurationError, ResolveConfigurationResult,
 };
 use serde::{Deserialize, Serialize};
 use std::{fmt, str::FromStr};
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", default)]
 pub struct Configuration {
     /// indent width
     pub indent_width: u8,
@@ -20,6 +21,64 @@ pub struct Configuration {
     pub wrap_close_brace: bool,
     /// nowrap before name argument
     pub nowrap_before_name: bool,
+    /// canonicalise escapes of strings
+    pub normalize_strings: bool,
+    /// `'''` strings without newline to `'` strings
+    pub collapse_multiline_strings: bool,
+    /// maximum line width
+    pub line_width: u32,
+    /// where to place operators when breaking binary expressions
+    pub operator_position: OperatorPosition,
+    /// minimum count of calls in a method chain to break it
+    pub method_chain_min_length: u8,
+    /// remove grouping parentheses which do not change the meaning
+    pub remove_redundant_parens: bool,
+    /// align `=` of consecutive assignments
+    pub align_assignments: bool,
+    /// align `#` of consecutive trailing comments
+    pub align_trailing_comments: bool,
+    /// whitespace after `#` of comments
+    pub comment_style: CommentStyle,
+    /// comments starting with one of these are kept as written
+    pub comment_prefixes: Vec<String>,
+    /// reflow blocks of comment lines to the line width
+    pub comment_wrap: bool,
+    /// lowercase prefixes and hex digits, no leading zeros of integers
+    pub normalize_numbers: bool,
+    /// newline of the output
+    pub new_line_kind: NewLineKind,
+    /// keep the UTF-8 byte order mark at the beginning of the file
+    pub preserve_bom: bool,
+    /// `space_inner_bracket` of arrays
+    pub array_space_inner_bracket: Option<bool>,
+    /// `wrap_close_brace` of arrays
+    pub array_wrap_close_brace: Option<bool>,
+    /// `align_colon` of dictionaries
+    pub dict_align_colon: Option<bool>,
+    /// `space_before_colon` of dictionaries
+    pub dict_space_before_colon: Option<bool>,
+    /// `space_inner_bracket` of dictionaries
+    pub dict_space_inner_brace: Option<bool>,
+    /// `wrap_close_brace` of dictionaries
+    pub dict_wrap_close_brace: Option<bool>,
+    /// `align_colon` of arguments
+    pub arguments_align_colon: Option<bool>,
+    /// `space_before_colon` of arguments
+    pub arguments_space_before_colon: Option<bool>,
+    /// `space_inner_bracket` of arguments
+    pub arguments_space_inner_paren: Option<bool>,
+    /// `wrap_close_brace` of arguments
+    pub arguments_wrap_close_brace: Option<bool>,
+    /// sort entries of dictionaries by their keys
+    pub dict_sort_keys: bool,
+    /// break dictionaries with more entries than this
+    pub dict_always_multiline: Option<u32>,
+    /// join lists written over several lines when they fit in one line
+    pub prefer_single_line: bool,
+    /// break arrays with more elements than this
+    pub multi_line_threshold: Option<u32>,
+    /// elements of multiline arrays of literals on one or filled lines
+    pub array_layout: ArrayLayout,
 }
 pub const DEFAULT_CONFIGURATION: Configuration = Configuration {
     indent_width: 2,
@@ -28,7 +87,196 @@ pub const DEFAULT_CONFIGURATION: Configuration = Configuration {
     space_inner_bracket: false,
     wrap_close_brace: true,
     nowrap_before_name: true,
+    normalize_strings: false,
+    collapse_multiline_strings: false,
+    line_width: 120,
+    operator_position: OperatorPosition::NextLine,
+    method_chain_min_length: 2,
+    remove_redundant_parens: false,
+    align_assignments: false,
+    align_trailing_comments: false,
+    comment_style: CommentStyle::Normalize,
+    comment_prefixes: Vec::new(),
+    comment_wrap: false,
+    normalize_numbers: false,
+    new_line_kind: NewLineKind::LineFeed,
+    preserve_bom: false,
+    array_space_inner_bracket: None,
+    array_wrap_close_brace: None,
+    dict_align_colon: None,
+    dict_space_before_colon: None,
+    dict_space_inner_brace: None,
+    dict_wrap_close_brace: None,
+    arguments_align_colon: None,
+    arguments_space_before_colon: None,
+    arguments_space_inner_paren: None,
+    arguments_wrap_close_brace: None,
+    dict_sort_keys: false,
+    dict_always_multiline: None,
+    prefer_single_line: false,
+    multi_line_threshold: None,
+    array_layout: ArrayLayout::OnePerLine,
 };
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OperatorPosition {
+    /// at the beginning of the continuation line
+    NextLine,
+    /// at the end of the broken line
+    SameLine,
+}
+impl FromStr for OperatorPosition {
+    type Err = ParseConfigurationError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "nextLine" => Ok(Self::NextLine),
+            "sameLine" => Ok(Self::SameLine),
+            _ => Err(ParseConfigurationError(s.into())),
+        }
+    }
+}
+impl fmt::Display for OperatorPosition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NextLine => write!(f, "nextLine"),
+            Self::SameLine => write!(f, "sameLine"),
+        }
+    }
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CommentStyle {
+    /// one space between `#` and the text
+    Normalize,
+    /// keep the whitespace after `#`, only insert a space when missing
+    Preserve,
+}
+impl FromStr for CommentStyle {
+    type Err = ParseConfigurationError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "normalize" => Ok(Self::Normalize),
+            "preserve" => Ok(Self::Preserve),
+            _ => Err(ParseConfigurationError(s.into())),
+        }
+    }
+}
+impl fmt::Display for CommentStyle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Normalize => write!(f, "normalize"),
+            Self::Preserve => write!(f, "preserve"),
+        }
+    }
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ArrayLayout {
+    /// one element per line
+    OnePerLine,
+    /// as many elements per line as fit, for arrays of literals
+    Fill,
+}
+impl FromStr for ArrayLayout {
+    type Err = ParseConfigurationError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "onePerLine" => Ok(Self::OnePerLine),
+            "fill" => Ok(Self::Fill),
+            _ => Err(ParseConfigurationError(s.into())),
+        }
+    }
+}
+impl fmt::Display for ArrayLayout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::OnePerLine => write!(f, "onePerLine"),
+            Self::Fill => write!(f, "fill"),
+        }
+    }
+}
+
+/// kind of bracketed lists
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListKind {
+    /// `[...]`
+    Array,
+    /// `{...}`
+    Dict,
+    /// `(...)` of a call
+    Arguments,
+}
+
+/// options of one kind of lists
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ListStyle {
+    pub align_colon: bool,
+    pub space_before_colon: bool,
+    pub space_inner_bracket: bool,
+    pub wrap_close_brace: bool,
+    pub sort_keys: bool,
+    pub always_multiline: Option<u32>,
+    pub prefer_single_line: bool,
+    pub fill: bool,
+}
+
+impl Configuration {
+    /// options of a kind of lists, the general ones unless overridden
+    pub fn list_style(&self, kind: ListKind) -> ListStyle {
+        let general = ListStyle {
+            align_colon: self.align_colon,
+            space_before_colon: self.space_before_colon,
+            space_inner_bracket: self.space_inner_bracket,
+            wrap_close_brace: self.wrap_close_brace,
+            sort_keys: false,
+            always_multiline: None,
+            prefer_single_line: self.prefer_single_line,
+            fill: false,
+        };
+        match kind {
+            ListKind::Array => ListStyle {
+                space_inner_bracket: self
+                    .array_space_inner_bracket
+                    .unwrap_or(general.space_inner_bracket),
+                wrap_close_brace: self
+                    .array_wrap_close_brace
+                    .unwrap_or(general.wrap_close_brace),
+                always_multiline: self.multi_line_threshold,
+                fill: self.array_layout == ArrayLayout::Fill,
+                ..general
+            },
+            ListKind::Dict => ListStyle {
+                align_colon: self.dict_align_colon.unwrap_or(general.align_colon),
+                space_before_colon: self
+                    .dict_space_before_colon
+                    .unwrap_or(general.space_before_colon),
+                space_inner_bracket: self
+                    .dict_space_inner_brace
+                    .unwrap_or(general.space_inner_bracket),
+                wrap_close_brace: self
+                    .dict_wrap_close_brace
+                    .unwrap_or(general.wrap_close_brace),
+                sort_keys: self.dict_sort_keys,
+                always_multiline: self.dict_always_multiline,
+                ..general
+            },
+            ListKind::Arguments => ListStyle {
+                align_colon: self.arguments_align_colon.unwrap_or(general.align_colon),
+                space_before_colon: self
+                    .arguments_space_before_colon
+                    .unwrap_or(general.space_before_colon),
+                space_inner_bracket: self
+                    .arguments_space_inner_paren
+                    .unwrap_or(general.space_inner_bracket),
+                wrap_close_brace: self
+                    .arguments_wrap_close_brace
+                    .unwrap_or(general.wrap_close_brace),
+                ..general
+            },
+        }
+    }
+}
+
 impl Default for Configuration {
     fn default() -> Self {
         DEFAULT_CONFIGURATION
@@ -45,12 +293,62 @@ pub fn resolve_config(
     if let Some(value) = global_config.indent_width {
         config.indent_width = value;
     }
+    if let Some(value) = global_config.line_width {
+        config.line_width = value;
+    }
+    if let Some(value) = global_config.new_line_kind {
+        config.new_line_kind = value;
+    }
     builder.get_nullable_value(&mut config.indent_width, "indentWidth");
     builder.get_nullable_value(&mut config.align_colon, "alignColon");
     builder.get_nullable_value(&mut config.space_before_colon, "spaceBeforeColon");
     builder.get_nullable_value(&mut config.space_inner_bracket, "spaceInnerBracket");
     builder.get_nullable_value(&mut config.wrap_close_brace, "wrapCloseBrace");
     builder.get_nullable_value(&mut config.nowrap_before_name, "nowrapBeforeName");
+    builder.get_nullable_value(&mut config.normalize_strings, "normalizeStrings");
+    builder.get_nullable_value(
+        &mut config.collapse_multiline_strings,
+        "collapseMultilineStrings",
+    );
+    builder.get_nullable_value(&mut config.line_width, "lineWidth");
+    builder.get_nullable_value(&mut config.operator_position, "operatorPosition");
+    builder.get_nullable_value(&mut config.method_chain_min_length, "methodChainMinLength");
+    builder.get_nullable_value(&mut config.remove_redundant_parens, "removeRedundantParens");
+    builder.get_nullable_value(&mut config.align_assignments, "alignAssignments");
+    builder.get_nullable_value(&mut config.align_trailing_comments, "alignTrailingComments");
+    builder.get_nullable_value(&mut config.comment_style, "commentStyle");
+    builder.get_string_list(&mut config.comment_prefixes, "commentPrefixes");
+    builder.get_nullable_value(&mut config.comment_wrap, "commentWrap");
+    builder.get_nullable_value(&mut config.normalize_numbers, "normalizeNumbers");
+    builder.get_nullable_value(&mut config.new_line_kind, "newLineKind");
+    builder.get_nullable_value(&mut config.preserve_bom, "preserveBom");
+    builder.get_optional_value(
+        &mut config.array_space_inner_bracket,
+        "arraySpaceInnerBracket",
+    );
+    builder.get_optional_value(&mut config.array_wrap_close_brace, "arrayWrapCloseBrace");
+    builder.get_optional_value(&mut config.dict_align_colon, "dictAlignColon");
+    builder.get_optional_value(&mut config.dict_space_before_colon, "dictSpaceBeforeColon");
+    builder.get_optional_value(&mut config.dict_space_inner_brace, "dictSpaceInnerBrace");
+    builder.get_optional_value(&mut config.dict_wrap_close_brace, "dictWrapCloseBrace");
+    builder.get_optional_value(&mut config.arguments_align_colon, "argumentsAlignColon");
+    builder.get_optional_value(
+        &mut config.arguments_space_before_colon,
+        "argumentsSpaceBeforeColon",
+    );
+    builder.get_optional_value(
+        &mut config.arguments_space_inner_paren,
+        "argumentsSpaceInnerParen",
+    );
+    builder.get_optional_value(
+        &mut config.arguments_wrap_close_brace,
+        "argumentsWrapCloseBrace",
+    );
+    builder.get_nullable_value(&mut config.dict_sort_keys, "dictSortKeys");
+    builder.get_optional_value(&mut config.dict_always_multiline, "dictAlwaysMultiline");
+    builder.get_nullable_value(&mut config.prefer_single_line, "preferSingleLine");
+    builder.get_optional_value(&mut config.multi_line_threshold, "multiLineThreshold");
+    builder.get_nullable_value(&mut config.array_layout, "arrayLayout");
 
     ResolveConfigurationResult {
         config,
@@ -88,32 +386,71 @@ impl ConfigurationBuilder {
             *store = value;
         }
     }
+    fn get_optional_value<T>(&mut self, store: &mut Option<T>, key: &'static str)
+    where
+        T: FromStr,
+        <T as FromStr>::Err: fmt::Display,
+    {
+        if let Some(value) = dprint_core::configuration::get_nullable_value(
+            &mut self.config,
+            key,
+            &mut self.diagnostics,
+        ) {
+            *store = Some(value);
+        }
+    }
+    fn get_string_list(&mut self, store: &mut Vec<String>, key: &'static str) {
+        let strings = match self.config.shift_remove(key) {
+            None | Some(ConfigKeyValue::Null) => return,
+            Some(ConfigKeyValue::Array(values)) => values
+                .into_iter()
+                .map(|value| match value {
+                    ConfigKeyValue::String(s) => Some(s),
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>(),
+            Some(_) => None,
+        };
+        match strings {
+            Some(strings) => *store = strings,
+            None => self.diagnostics.push(ConfigurationDiagnostic {
+                property_name: key.into(),
+                message: "Expected an array of strings".into(),
+            }),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use dprint_core::configuration::{
-        resolve_global_config, ConfigKeyValue, ResolveGlobalConfigOptions,
-    };
-    use std::default::Default;
+    use dprint_core::configuration::resolve_global_config;
 
     #[test]
     fn resolve_null_config() {
-        let global_config_default = ResolveGlobalConfigOptions::default();
-        let global_config =
-            resolve_global_config(ConfigKeyMap::new(), &global_config_default).config;
+        let global_config = resolve_global_config(&mut ConfigKeyMap::new()).config;
         assert_eq!(
             resolve_config(ConfigKeyMap::new(), &global_config).config,
             DEFAULT_CONFIGURATION
         );
     }
 
+    #[test]
+    fn resolve_invalid_comment_prefixes() {
+        let global_config = resolve_global_config(&mut ConfigKeyMap::new()).config;
+        let key_map = ConfigKeyMap::from([(
+            "commentPrefixes".to_string(),
+            ConfigKeyValue::Array(vec![ConfigKeyValue::Number(1)]),
+        )]);
+        let result = resolve_config(key_map, &global_config);
+        assert_eq!(result.config, DEFAULT_CONFIGURATION);
+        assert_eq!(result.diagnostics.len(), 1);
+        assert_eq!(result.diagnostics[0].property_name, "commentPrefixes");
+    }
+
     #[test]
     fn resolve_full_config() {
-        let global_config_default = ResolveGlobalConfigOptions::default();
-        let global_config =
-            resolve_global_config(ConfigKeyMap::new(), &global_config_default).config;
+        let global_config = resolve_global_config(&mut ConfigKeyMap::new()).config;
 
         let changed_config = Configuration {
             indent_width: DEFAULT_CONFIGURATION.indent_width * 2,
@@ -122,6 +459,35 @@ mod tests {
             space_inner_bracket: !DEFAULT_CONFIGURATION.space_inner_bracket,
             wrap_close_brace: !DEFAULT_CONFIGURATION.wrap_close_brace,
             nowrap_before_name: !DEFAULT_CONFIGURATION.wrap_close_brace,
+            normalize_strings: !DEFAULT_CONFIGURATION.normalize_strings,
+            collapse_multiline_strings: !DEFAULT_CONFIGURATION.collapse_multiline_strings,
+            line_width: DEFAULT_CONFIGURATION.line_width / 2,
+            operator_position: OperatorPosition::SameLine,
+            method_chain_min_length: DEFAULT_CONFIGURATION.method_chain_min_length + 1,
+            remove_redundant_parens: !DEFAULT_CONFIGURATION.remove_redundant_parens,
+            align_assignments: !DEFAULT_CONFIGURATION.align_assignments,
+            align_trailing_comments: !DEFAULT_CONFIGURATION.align_trailing_comments,
+            comment_style: CommentStyle::Preserve,
+            comment_prefixes: vec!["SPDX-".into()],
+            comment_wrap: !DEFAULT_CONFIGURATION.comment_wrap,
+            normalize_numbers: !DEFAULT_CONFIGURATION.normalize_numbers,
+            new_line_kind: NewLineKind::CarriageReturnLineFeed,
+            preserve_bom: !DEFAULT_CONFIGURATION.preserve_bom,
+            array_space_inner_bracket: Some(true),
+            array_wrap_close_brace: Some(false),
+            dict_align_colon: Some(true),
+            dict_space_before_colon: Some(false),
+            dict_space_inner_brace: Some(true),
+            dict_wrap_close_brace: Some(false),
+            arguments_align_colon: Some(true),
+            arguments_space_before_colon: Some(false),
+            arguments_space_inner_paren: Some(true),
+            arguments_wrap_close_brace: Some(false),
+            dict_sort_keys: !DEFAULT_CONFIGURATION.dict_sort_keys,
+            dict_always_multiline: Some(3),
+            prefer_single_line: !DEFAULT_CONFIGURATION.prefer_single_line,
+            multi_line_threshold: Some(5),
+            array_layout: ArrayLayout::Fill,
         };
 
         let key_map = vec![
@@ -149,6 +515,116 @@ mod tests {
                 "nowrapBeforeName",
                 ConfigKeyValue::Bool(changed_config.nowrap_before_name),
             ),
+            (
+                "normalizeStrings",
+                ConfigKeyValue::Bool(changed_config.normalize_strings),
+            ),
+            (
+                "collapseMultilineStrings",
+                ConfigKeyValue::Bool(changed_config.collapse_multiline_strings),
+            ),
+            (
+                "lineWidth",
+                ConfigKeyValue::Number(changed_config.line_width as i32),
+            ),
+            (
+                "operatorPosition",
+                ConfigKeyValue::String(changed_config.operator_position.to_string()),
+            ),
+            (
+                "methodChainMinLength",
+                ConfigKeyValue::Number(changed_config.method_chain_min_length as i32),
+            ),
+            (
+                "removeRedundantParens",
+                ConfigKeyValue::Bool(changed_config.remove_redundant_parens),
+            ),
+            (
+                "alignAssignments",
+                ConfigKeyValue::Bool(changed_config.align_assignments),
+            ),
+            (
+                "alignTrailingComments",
+                ConfigKeyValue::Bool(changed_config.align_trailing_comments),
+            ),
+            (
+                "commentStyle",
+                ConfigKeyValue::String(changed_config.comment_style.to_string()),
+            ),
+            (
+                "commentPrefixes",
+                ConfigKeyValue::Array(vec![ConfigKeyValue::String("SPDX-".into())]),
+            ),
+            (
+                "commentWrap",
+                ConfigKeyValue::Bool(changed_config.comment_wrap),
+            ),
+            (
+                "normalizeNumbers",
+                ConfigKeyValue::Bool(changed_config.normalize_numbers),
+            ),
+            (
+                "newLineKind",
+                ConfigKeyValue::String(changed_config.new_line_kind.to_string()),
+            ),
+            (
+                "preserveBom",
+                ConfigKeyValue::Bool(changed_config.preserve_bom),
+            ),
+            (
+                "arraySpaceInnerBracket",
+                ConfigKeyValue::Bool(changed_config.array_space_inner_bracket.unwrap()),
+            ),
+            (
+                "arrayWrapCloseBrace",
+                ConfigKeyValue::Bool(changed_config.array_wrap_close_brace.unwrap()),
+            ),
+            (
+                "dictAlignColon",
+                ConfigKeyValue::Bool(changed_config.dict_align_colon.unwrap()),
+            ),
+            (
+                "dictSpaceBeforeColon",
+                ConfigKeyValue::Bool(changed_config.dict_space_before_colon.unwrap()),
+            ),
+            (
+                "dictSpaceInnerBrace",
+                ConfigKeyValue::Bool(changed_config.dict_space_inner_brace.unwrap()),
+            ),
+            (
+                "dictWrapCloseBrace",
+                ConfigKeyValue::Bool(changed_config.dict_wrap_close_brace.unwrap()),
+            ),
+            (
+                "argumentsAlignColon",
+                ConfigKeyValue::Bool(changed_config.arguments_align_colon.unwrap()),
+            ),
+            (
+                "argumentsSpaceBeforeColon",
+                ConfigKeyValue::Bool(changed_config.arguments_space_before_colon.unwrap()),
+            ),
+            (
+                "argumentsSpaceInnerParen",
+                ConfigKeyValue::Bool(changed_config.arguments_space_inner_paren.unwrap()),
+            ),
+            (
+                "argumentsWrapCloseBrace",
+                ConfigKeyValue::Bool(changed_config.arguments_wrap_close_brace.unwrap()),
+            ),
+            (
+                "dictSortKeys",
+                ConfigKeyValue::Bool(changed_config.dict_sort_keys),
+            ),
+            ("dictAlwaysMultiline", ConfigKeyValue::Number(3)),
+            (
+                "preferSingleLine",
+                ConfigKeyValue::Bool(changed_config.prefer_single_line),
+            ),
+            ("multiLineThreshold", ConfigKeyValue::Number(5)),
+            (
+                "arrayLayout",
+                ConfigKeyValue::String(changed_config.array_layout.to_string()),
+            ),
         ]
         .into_iter()
         .map(|(k, v)| (k.to_string(), v))
@@ -159,4 +635,21 @@ mod tests {
             changed_config
         );
     }
+
+    #[test]
+    fn list_style_fallback() {
+        let config = Configuration {
+            align_colon: true,
+            dict_align_colon: Some(false),
+            arguments_wrap_close_brace: Some(false),
+            dict_sort_keys: true,
+            ..DEFAULT_CONFIGURATION
+        };
+        let array = config.list_style(ListKind::Array);
+        let dict = config.list_style(ListKind::Dict);
+        let arguments = config.list_style(ListKind::Arguments);
+        assert!(array.align_colon && !dict.align_colon && arguments.align_colon);
+        assert!(array.wrap_close_brace && dict.wrap_close_brace && !arguments.wrap_close_brace);
+        assert!(!array.sort_keys && dict.sort_keys && !arguments.sort_keys);
+    }
 }
@@ -0,0 +1,332 @@
+use crate::ast::{self, Arguments, Expr, ExprKind, Span, Statement, Visitor};
+use crate::checker::{Diagnostic, Severity, Types};
+use crate::configuration::Configuration;
+use crate::parser::ParseError;
+use crate::refactor::{apply_edits, StringStyle, TextEdit};
+use crate::version::{Version, VersionRequirement};
+use std::path::{Path, PathBuf};
+
+/// which rewrites of deprecated idioms to apply
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FixOptions {
+    /// `meson.source_root()` to `meson.project_source_root()`
+    pub source_root: bool,
+    /// `meson.build_root()` to `meson.project_build_root()`
+    pub build_root: bool,
+    /// `get_pkgconfig_variable('x')` to `get_variable(pkgconfig: 'x')`
+    pub pkgconfig_variable: bool,
+    /// `'@0@'.format(x)` to `f'@x@'` when every argument is a `str`, `int`
+    /// or `bool`
+    pub format_string: bool,
+    /// `meson_options.txt` to `meson.options`
+    pub options_file: bool,
+}
+impl Default for FixOptions {
+    fn default() -> Self {
+        Self {
+            source_root: true,
+            build_root: true,
+            pkgconfig_variable: true,
+            format_string: true,
+            options_file: true,
+        }
+    }
+}
+
+/// Rewrite deprecated idioms and format the result like `format_text`,
+/// returns the new text and warnings for the `.format()` calls left alone.
+///
+/// A rewrite is only applied when the lowest version allowed by
+/// `meson_version` supports the replacement. f-strings only accept strings,
+/// integers and booleans, so a `.format()` call is only rewritten when its
+/// arguments are known to be one of them.
+pub fn fix_text(
+    file_text: &str,
+    config: &Configuration,
+    options: &FixOptions,
+    meson_version: Option<&VersionRequirement>,
+) -> Result<(String, Vec<Diagnostic>), ParseError> {
+    let file = ast::parse_file(file_text)?;
+    let mut fixer = Fixer {
+        text: file_text,
+        options,
+        minimum: meson_version.and_then(VersionRequirement::minimum),
+        types: Types::new(),
+        edits: vec![],
+        skipped: vec![],
+    };
+    ast::walk_statements(&mut fixer, &file.statements);
+
+    let text = apply_edits(file_text, &fixer.edits);
+    let formatted = crate::format_text::format_text(&text, config)?;
+    Ok((formatted.unwrap_or(text), fixer.skipped))
+}
+
+/// new path of a `meson_options.txt` file if it should be renamed
+pub fn fix_file_name(
+    path: &Path,
+    options: &FixOptions,
+    meson_version: Option<&VersionRequirement>,
+) -> Option<PathBuf> {
+    let allowed = meson_version
+        .and_then(VersionRequirement::minimum)
+        .is_some_and(|v| v >= Version(vec![1, 1]));
+    if options.options_file && allowed && path.file_name()? == "meson_options.txt" {
+        Some(path.with_file_name("meson.options"))
+    } else {
+        None
+    }
+}
+
+struct Fixer<'a> {
+    text: &'a str,
+    options: &'a FixOptions,
+    minimum: Option<Version>,
+    types: Types,
+    edits: Vec<TextEdit>,
+    /// `.format()` calls which were not rewritten
+    skipped: Vec<Diagnostic>,
+}
+
+impl<'a> Fixer<'a> {
+    fn allows(&self, since: &[u32]) -> bool {
+        self.minimum
+            .as_ref()
+            .is_some_and(|v| *v >= Version(since.to_vec()))
+    }
+
+    fn rename(&mut self, name: &ast::Ident, new_name: &str) {
+        self.edits.push(TextEdit {
+            span: name.span,
+            new_text: new_name.into(),
+        });
+    }
+
+    fn fix_pkgconfig_variable(&mut self, name: &ast::Ident, arguments: &Arguments) {
+        let variable = match arguments.positional.as_slice() {
+            [variable] => variable,
+            _ => return,
+        };
+        self.rename(name, "get_variable");
+        self.edits.push(TextEdit {
+            span: Span {
+                start: variable.span.start,
+                end: variable.span.start,
+            },
+            new_text: "pkgconfig: ".into(),
+        });
+        for keyword in &arguments.keywords {
+            let renamed = match keyword.name.name.as_str() {
+                "default" => "default_value",
+                "define_variable" => "pkgconfig_define",
+                _ => continue,
+            };
+            self.rename(&keyword.name, renamed);
+        }
+    }
+
+    /// returns whether the call was rewritten
+    fn fix_format_string(&mut self, expr: &Expr, arguments: &Arguments) -> bool {
+        let result = crate::refactor::convert(self.text, expr, StringStyle::FString);
+        let edit = match result {
+            Ok(edit) => edit,
+            Err(e) => {
+                self.skip(format!("`.format()` not rewritten, {}", e), expr.span);
+                return false;
+            }
+        };
+        let unknown = arguments.positional.iter().find(|argument| {
+            let argument_type = self.types.type_of(argument);
+            !matches!(argument_type.as_deref(), Some("str" | "int" | "bool"))
+        });
+        match unknown {
+            Some(argument) => {
+                self.skip(
+                    format!(
+                        "`.format()` not rewritten, `{}` is not known to be a str, int or bool",
+                        argument.span.text(self.text)
+                    ),
+                    argument.span,
+                );
+                false
+            }
+            None => {
+                self.edits.push(edit);
+                true
+            }
+        }
+    }
+
+    fn skip(&mut self, message: String, span: Span) {
+        self.skipped.push(Diagnostic {
+            severity: Severity::Warning,
+            message,
+            span,
+        });
+    }
+}
+
+impl<'a> Visitor for Fixer<'a> {
+    fn visit_statement(&mut self, statement: &Statement) -> bool {
+        self.types.enter(statement);
+        true
+    }
+
+    fn leave_statement(&mut self, statement: &Statement) {
+        self.types.leave(statement);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) -> bool {
+        let (object, name, arguments) = match &expr.kind {
+            ExprKind::MethodCall {
+                object,
+                name,
+                arguments,
+            } => (object, name, arguments),
+            _ => return true,
+        };
+        let on_meson = object.kind.as_identifier() == Some("meson");
+        match name.name.as_str() {
+            "source_root" if on_meson && self.options.source_root && self.allows(&[0, 56]) => {
+                self.rename(name, "project_source_root");
+            }
+            "build_root" if on_meson && self.options.build_root && self.allows(&[0, 56]) => {
+                self.rename(name, "project_build_root");
+            }
+            "get_pkgconfig_variable"
+                if self.options.pkgconfig_variable && self.allows(&[0, 51]) =>
+            {
+                self.fix_pkgconfig_variable(name, arguments);
+            }
+            "format" if self.options.format_string && self.allows(&[0, 58]) => {
+                let multiline = matches!(&object.kind, ExprKind::String(s) if s.multiline);
+                // the rewritten call replaces the fixes inside it
+                if (!multiline || self.allows(&[0, 63])) && self.fix_format_string(expr, arguments)
+                {
+                    return false;
+                }
+            }
+            _ => {}
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::DEFAULT_CONFIGURATION;
+
+    fn fix(text: &str, options: &FixOptions, version: &str) -> String {
+        let version = version.parse().unwrap();
+        fix_text(text, &DEFAULT_CONFIGURATION, options, Some(&version))
+            .unwrap()
+            .0
+    }
+
+    #[test]
+    fn fix_deprecated_calls() {
+        let text = "a=meson.source_root()\nb=meson.build_root()/'x'\nc=dep.get_pkgconfig_variable('libdir',default:'/usr')\nd=other.source_root()";
+        assert_eq!(
+            fix(text, &FixOptions::default(), ">=0.56"),
+            "a = meson.project_source_root()\nb = meson.project_build_root() / 'x'\nc = dep.get_variable(pkgconfig: 'libdir', default_value: '/usr')\nd = other.source_root()"
+        );
+        assert_eq!(
+            fix(text, &FixOptions::default(), ">=0.51"),
+            "a = meson.source_root()\nb = meson.build_root() / 'x'\nc = dep.get_variable(pkgconfig: 'libdir', default_value: '/usr')\nd = other.source_root()"
+        );
+        let options = FixOptions {
+            build_root: false,
+            pkgconfig_variable: false,
+            ..FixOptions::default()
+        };
+        assert_eq!(
+            fix(text, &options, ">=1.0"),
+            "a = meson.project_source_root()\nb = meson.build_root() / 'x'\nc = dep.get_pkgconfig_variable('libdir', default: '/usr')\nd = other.source_root()"
+        );
+        assert_eq!(
+            fix_text(text, &DEFAULT_CONFIGURATION, &FixOptions::default(), None)
+                .unwrap()
+                .0,
+            crate::format_text::format_text(text, &DEFAULT_CONFIGURATION)
+                .unwrap()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn fix_format_strings() {
+        // `a` is a str, `b` an int and `c` a bool
+        let head = "a='x'\nb=1\nc=true\n";
+        for (src, dst) in [
+            ("x='@0@/@1@'.format(a,b)", "x = f'@a@/@b@'"),
+            ("x='@1@@0@\\'s'.format(a,c)", "x = f'@c@@a@\\'s'"),
+            ("x='a@b'.format()", "x = f'a@b'"),
+            ("x='\\x41@0@@'.format(a)", "x = f'\\x41@a@@'"),
+            ("x='@0@'.format(a.b())", "x = '@0@'.format(a.b())"),
+            ("x='@0@ @y@'.format(a)", "x = '@0@ @y@'.format(a)"),
+            ("x='@1@'.format(a)", "x = '@1@'.format(a)"),
+            ("x='@0@'.format(a, b: 1)", "x = '@0@'.format(a, b: 1)"),
+            ("x='''@0@'''.format(a)", "x = '''@0@'''.format(a)"),
+            // f-strings reject objects `.format()` accepts
+            ("x='@0@'.format(d)", "x = '@0@'.format(d)"),
+            ("d=[a]\nx='@0@'.format(d)", "d = [a]\nx = '@0@'.format(d)"),
+            (
+                "foreach d : [a]\nx='@0@'.format(d)\nendforeach",
+                "foreach d : [a]\n  x = '@0@'.format(d)\nendforeach",
+            ),
+            (
+                "d=meson.project_name()\nx='@0@'.format(d)",
+                "d = meson.project_name()\nx = f'@d@'",
+            ),
+        ] {
+            let src = format!("{}{}", head, src);
+            let dst = format!("a = 'x'\nb = 1\nc = true\n{}", dst);
+            assert_eq!(fix(&src, &FixOptions::default(), ">=0.58"), dst);
+        }
+        assert_eq!(
+            fix("a='x'\nx='@0@'.format(a)", &FixOptions::default(), ">=0.57"),
+            "a = 'x'\nx = '@0@'.format(a)"
+        );
+
+        let version = ">=0.58".parse().unwrap();
+        let text = "x='@0@'.format(d)\ny='@1@'.format(meson.source_root())";
+        let options = FixOptions::default();
+        let (text, skipped) =
+            fix_text(text, &DEFAULT_CONFIGURATION, &options, Some(&version)).unwrap();
+        assert_eq!(
+            text,
+            "x = '@0@'.format(d)\ny = '@1@'.format(meson.project_source_root())"
+        );
+        assert_eq!(
+            skipped.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            [
+                "1:16: warning: `.format()` not rewritten, `d` is not known to be a str, int or bool",
+                "2:3: warning: `.format()` not rewritten, argument at 2:16 is not a plain identifier",
+            ]
+        );
+    }
+
+    #[test]
+    fn fix_options_file() {
+        let options = FixOptions::default();
+        let path = Path::new("src/meson_options.txt");
+        assert_eq!(
+            fix_file_name(path, &options, Some(&">=1.1".parse().unwrap())),
+            Some(PathBuf::from("src/meson.options"))
+        );
+        assert_eq!(
+            fix_file_name(path, &options, Some(&">=1.0".parse().unwrap())),
+            None
+        );
+        assert_eq!(fix_file_name(path, &options, None), None);
+        assert_eq!(
+            fix_file_name(
+                Path::new("meson.build"),
+                &options,
+                Some(&">=1.1".parse().unwrap())
+            ),
+            None
+        );
+    }
+}
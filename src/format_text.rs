@@ -1,17 +1,133 @@
 use crate::configuration::Configuration;
 use crate::parser::ParseError;
+use dprint_core::configuration::resolve_new_line_kind;
+
+const BOM: char = '\u{feff}';
 
 pub fn format_text(file_text: &str, config: &Configuration) -> Result<Option<String>, ParseError> {
-    let mut chars = file_text.chars();
+    let (bom, text) = match file_text.strip_prefix(BOM) {
+        Some(text) => (true, text),
+        None => (false, file_text),
+    };
+    // the parser only knows `\n`
+    let text = text.replace("\r\n", "\n").replace('\r', "\n");
+    let mut chars = text.chars();
     let mut formated = String::new();
     crate::parser::parse(config, &mut chars, &mut formated)?;
+    // the formatter reads chars as a stream and lets through some statements
+    // meson rejects, like `x = a b`
+    crate::ast::parse_file(&text)?;
+    if config.comment_wrap {
+        formated = crate::reflow::reflow_comments(&formated, config);
+    }
+    if config.align_assignments || config.align_trailing_comments {
+        formated = crate::align::align(&formated, config);
+    }
+    let newline = resolve_new_line_kind(file_text, config.new_line_kind);
+    if newline != "\n" {
+        formated = formated.replace('\n', newline);
+    }
+    if bom && config.preserve_bom {
+        formated.insert(0, BOM);
+    }
     Ok(Some(formated))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::configuration::DEFAULT_CONFIGURATION;
+    use crate::configuration::{ArrayLayout, CommentStyle, DEFAULT_CONFIGURATION};
+    use dprint_core::configuration::NewLineKind;
+
+    /// build files of the test data
+    fn data_files(dir: &std::path::Path, files: &mut Vec<std::path::PathBuf>) {
+        for entry in std::fs::read_dir(dir).unwrap().flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                data_files(&path, files);
+            } else if path.extension().is_some_and(|e| e == "build") {
+                files.push(path);
+            }
+        }
+    }
+
+    #[test]
+    fn parsers_agree() {
+        let mut files = vec![];
+        let data = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data");
+        data_files(&data, &mut files);
+        let mut texts = files
+            .iter()
+            .map(|path| std::fs::read_to_string(path).unwrap())
+            .collect::<Vec<_>>();
+        texts.extend(
+            [
+                "x = c ? a : b ? d : e",
+                "x = a not in b and not c in d",
+                "x = -(-1) - -a.b()[0]",
+                "x = f'@a@' + '''b\nc'''",
+                "x = (a\n+ b)",
+                "x = [a, # c\n  b]",
+                "x = {\n  'a': 1, # one\n  'b': 2,\n}",
+                "f(\n  a,\n  # only\n  b: c,\n)",
+                "foreach k, v : d\n  if not(k)\n    continue\n  elif v\n    break\n  endif\nendforeach",
+                "x = [a # c\n  + b]",
+                // invalid
+                "x = 1 +\n2",
+                "x = a.",
+                "x = a[",
+                "x = a)",
+                "if\nendif",
+                "if a\n",
+                "x = 'b",
+                "x = \"b\"",
+                "x = $",
+                "x = 0x",
+                "x = +1",
+                "x = a\n* b",
+                "x = a\nand b",
+                "x = (a)\n  .b()",
+            ]
+            .map(String::from),
+        );
+        // the formatter alone reads these and would change the text of some
+        let rejected = [
+            "foo(a b)",
+            "x = a b",
+            "x = 1 2",
+            "x = [1 2]",
+            "foreach x
+endforeach",
+            "x = a ? b",
+            "x = {a}",
+            "f(a, b: 1, c)",
+            "x.y = 1",
+            "x[0] = 1",
+            "endif",
+            "x = ()",
+            "()",
+            "f(a:)",
+            "foo((a, b))",
+            "''s''",
+            "{{}, []}",
+        ];
+        for text in rejected {
+            assert!(
+                format_text(text, &DEFAULT_CONFIGURATION).is_err(),
+                "{}",
+                text
+            );
+        }
+        texts.extend(rejected.map(String::from));
+        for text in texts {
+            assert_eq!(
+                format_text(&text, &DEFAULT_CONFIGURATION).is_ok(),
+                crate::ast::parse_file(&text).is_ok(),
+                "{}",
+                text
+            );
+        }
+    }
 
     #[test]
     fn format_auxiliary() {
@@ -39,6 +155,486 @@ mod tests {
         });
     }
 
+    #[test]
+    fn format_comments() {
+        let config = Configuration {
+            comment_style: CommentStyle::Preserve,
+            comment_prefixes: vec!["SPDX-".into()],
+            ..DEFAULT_CONFIGURATION
+        };
+        vec![
+            ("#text", "# text", "# text"),
+            ("#   +--+\n#   |  |", "# +--+\n# |  |", "#   +--+\n#   |  |"),
+            (
+                "#!/usr/bin/env meson",
+                "#!/usr/bin/env meson",
+                "#!/usr/bin/env meson",
+            ),
+            ("#####\n#- a -", "#####\n#- a -", "#####\n#- a -"),
+            ("#=== a ===\n#***", "#=== a ===\n#***", "#=== a ===\n#***"),
+            ("#(x)", "# (x)", "# (x)"),
+            ("#'x' #[a] #@foo@", "# 'x' #[a] #@foo@", "# 'x' #[a] #@foo@"),
+            ("#  [a, b]", "# [a, b]", "#  [a, b]"),
+            (
+                "#  SPDX-License-Identifier: MIT",
+                "# SPDX-License-Identifier: MIT",
+                "#  SPDX-License-Identifier: MIT",
+            ),
+            (
+                "x = 1 #    foo('a')",
+                "x = 1 # foo('a')",
+                "x = 1 #    foo('a')",
+            ),
+        ]
+        .into_iter()
+        .for_each(|(src, normalized, preserved)| {
+            for (config, dst) in [(&DEFAULT_CONFIGURATION, normalized), (&config, preserved)] {
+                assert_eq!(format_text(src, config).unwrap().unwrap(), dst);
+                assert_eq!(format_text(dst, config).unwrap().unwrap(), dst);
+            }
+        });
+    }
+
+    #[test]
+    fn format_wrapped_comments() {
+        let config = Configuration {
+            comment_wrap: true,
+            line_width: 24,
+            ..DEFAULT_CONFIGURATION
+        };
+        vec![
+            (
+                "#Build the library\n#and its tests.\nlib=library('a')#keep trailing comments long\n",
+                "# Build the library and\n# its tests.\nlib = library('a') # keep trailing comments long\n",
+            ),
+            (
+                "#- one two three four five six\n#- seven",
+                "#- one two three four five six\n#- seven",
+            ),
+            (
+                "# - one two three four five six\n# - seven",
+                "# - one two three four\n#   five six\n# - seven",
+            ),
+            // the whitespace after `#` is normalized before reflowing
+            (
+                "#   indented text\n#  and more",
+                "# indented text and more",
+            ),
+        ]
+        .into_iter()
+        .for_each(|(src, dst)| {
+            assert_eq!(format_text(src, &config).unwrap().unwrap(), dst);
+            assert_eq!(format_text(dst, &config).unwrap().unwrap(), dst);
+        });
+
+        let config = Configuration {
+            comment_style: CommentStyle::Preserve,
+            ..config
+        };
+        assert_eq!(
+            format_text("#   indented text\n#  and more", &config)
+                .unwrap()
+                .unwrap(),
+            "#   indented text\n#  and more"
+        );
+    }
+
+    #[test]
+    fn format_numbers() {
+        let config = Configuration {
+            normalize_numbers: true,
+            ..DEFAULT_CONFIGURATION
+        };
+        vec![
+            ("x=0XFF+0o0755", "x = 0XFF + 0o0755", "x = 0xff + 0o755"),
+            ("x=[0B1010,-007]", "x = [0B1010, -007]", "x = [0b1010, -7]"),
+            ("x1=a1-0x1F", "x1 = a1 - 0x1F", "x1 = a1 - 0x1f"),
+        ]
+        .into_iter()
+        .for_each(|(src, kept, normalized)| {
+            assert_eq!(
+                format_text(src, &DEFAULT_CONFIGURATION).unwrap().unwrap(),
+                kept
+            );
+            assert_eq!(format_text(src, &config).unwrap().unwrap(), normalized);
+            assert_eq!(
+                format_text(normalized, &config).unwrap().unwrap(),
+                normalized
+            );
+        });
+
+        vec![
+            ("x = 0x", "`0x`", 1, 5),
+            ("if true\n  y = [1, 0b12]\nendif", "`0b12`", 2, 11),
+            ("z = 12ab", "`12ab`", 1, 5),
+        ]
+        .into_iter()
+        .for_each(|(src, found, line, column)| {
+            assert_eq!(
+                format_text(src, &config),
+                Err(ParseError::UnexpectedSyntax {
+                    found: found.into(),
+                    expected: "an integer literal".into(),
+                    line,
+                    column,
+                })
+            );
+        });
+    }
+
+    #[test]
+    fn format_illegal_chars() {
+        vec![
+            ("x = \"a\"", "`\"`", "a string quoted with `'`", 1, 5),
+            ("if x\n  foo(a, b;)\nendif", "`;`", "a meson token", 2, 11),
+            ("x = $a", "`$`", "a meson token", 1, 5),
+            ("x = [a@b]", "`@`", "a meson token", 1, 7),
+            (
+                "# é\nnaïve = 1",
+                "`ï`",
+                "an identifier of ASCII letters, digits and `_`",
+                2,
+                3,
+            ),
+        ]
+        .into_iter()
+        .for_each(|(src, found, expected, line, column)| {
+            assert_eq!(
+                format_text(src, &DEFAULT_CONFIGURATION),
+                Err(ParseError::UnexpectedSyntax {
+                    found: found.into(),
+                    expected: expected.into(),
+                    line,
+                    column,
+                })
+            );
+        });
+        assert_eq!(
+            format_text("x = 'a\"b' # \"c\"", &DEFAULT_CONFIGURATION)
+                .unwrap()
+                .unwrap(),
+            "x = 'a\"b' # \"c\""
+        );
+    }
+
+    #[test]
+    fn format_line_endings() {
+        let auto = Configuration {
+            new_line_kind: NewLineKind::Auto,
+            ..DEFAULT_CONFIGURATION
+        };
+        let crlf = Configuration {
+            new_line_kind: NewLineKind::CarriageReturnLineFeed,
+            preserve_bom: true,
+            ..DEFAULT_CONFIGURATION
+        };
+        vec![
+            (&DEFAULT_CONFIGURATION, "x=1\r\ny=2\r", "x = 1\ny = 2\n"),
+            (&DEFAULT_CONFIGURATION, "\u{feff}x=1\n", "x = 1\n"),
+            (&auto, "x=[\r\n1]\r\n", "x = [\r\n  1,\r\n]\r\n"),
+            (&auto, "x=1\ny=2\n", "x = 1\ny = 2\n"),
+            (&crlf, "\u{feff}x=1\n#a\n", "\u{feff}x = 1\r\n# a\r\n"),
+            (&crlf, "x='''a\r\nb'''", "x = '''a\r\nb'''"),
+        ]
+        .into_iter()
+        .for_each(|(config, src, dst)| {
+            assert_eq!(format_text(src, config).unwrap().unwrap(), dst);
+            assert_eq!(format_text(dst, config).unwrap().unwrap(), dst);
+        });
+    }
+
+    #[test]
+    fn format_list_kinds() {
+        let config = Configuration {
+            space_inner_bracket: true,
+            dict_space_inner_brace: Some(false),
+            arguments_space_inner_paren: Some(false),
+            ..DEFAULT_CONFIGURATION
+        };
+        assert_eq!(
+            format_text("x = f([1], {'a': 2})", &config)
+                .unwrap()
+                .unwrap(),
+            "x = f([ 1 ], {'a': 2})"
+        );
+
+        let config = Configuration {
+            dict_sort_keys: true,
+            dict_always_multiline: Some(1),
+            ..DEFAULT_CONFIGURATION
+        };
+        vec![
+            ("x = {'b': 1, 'a': 2}", "x = {\n  'a': 2,\n  'b': 1,\n}"),
+            ("x = {'b': 1}", "x = {'b': 1}"),
+            ("f(b: 1, a: 2)", "f(b: 1, a: 2)"),
+            (
+                "x = {\n  # b\n  'b': 1, # c\n  'a': 2,\n  # end\n}",
+                "x = {\n  'a': 2,\n  # b\n  'b': 1, # c\n  # end\n}",
+            ),
+        ]
+        .into_iter()
+        .for_each(|(src, dst)| {
+            assert_eq!(format_text(src, &config).unwrap().unwrap(), dst);
+            assert_eq!(format_text(dst, &config).unwrap().unwrap(), dst);
+        });
+    }
+
+    #[test]
+    fn format_list_thresholds() {
+        let config = Configuration {
+            line_width: 30,
+            prefer_single_line: true,
+            multi_line_threshold: Some(2),
+            ..DEFAULT_CONFIGURATION
+        };
+        vec![
+            ("x = [\n  1,\n  2,\n]", "x = [1, 2]"),
+            ("x = [1, 2, 3]", "x = [\n  1,\n  2,\n  3,\n]"),
+            ("f(\n  a: 1,\n  b: 2,\n  c: 3,\n)", "f(a: 1, b: 2, c: 3)"),
+            ("x = [\n  [\n    1,\n  ],\n]", "x = [[1]]"),
+            ("x = [\n  1, # c\n]", "x = [\n  1, # c\n]"),
+            (
+                "x = [\n  'aaaaaaaaaaaaaa',\n  'bbbbbbbbbbbbbb',\n]",
+                "x = [\n  'aaaaaaaaaaaaaa',\n  'bbbbbbbbbbbbbb',\n]",
+            ),
+            // the text before and after the list is on the same line
+            (
+                "long_name_dep = dependency(\n  'zlib',\n  'abc',\n)",
+                "long_name_dep = dependency('zlib',\n  'abc',\n)",
+            ),
+            (
+                "x = f(\n  'aaaaaaaa',\n).get_variable('b')",
+                "x = f(\n  'aaaaaaaa',\n).get_variable('b')",
+            ),
+            (
+                "x = f(\n  'aaaaaaaa',\n).get('b')",
+                "x = f('aaaaaaaa').get('b')",
+            ),
+            (
+                "if a\n  foo = [\n    'aaaaaaaaaaaaaa',\n    'b',\n  ]\nendif",
+                "if a\n  foo = [\n    'aaaaaaaaaaaaaa',\n    'b',\n  ]\nendif",
+            ),
+            (
+                "foreach a : [\n  1,\n  2,\n]\nendforeach",
+                "foreach a : [1, 2]\nendforeach",
+            ),
+            (
+                "foreach long_name : [\n  'aaaaaaa',\n]\nendforeach",
+                "foreach long_name : [\n    'aaaaaaa',\n  ]\nendforeach",
+            ),
+        ]
+        .into_iter()
+        .for_each(|(src, dst)| {
+            assert_eq!(format_text(src, &config).unwrap().unwrap(), dst);
+            assert_eq!(format_text(dst, &config).unwrap().unwrap(), dst);
+        });
+
+        // comments end the line of a list
+        vec![
+            ("x = [1, # a\n2, # b\n]", "x = [\n  1, # a\n  2, # b\n]"),
+            ("x = [ # a\n  1]", "x = [\n  # a\n  1,\n]"),
+        ]
+        .into_iter()
+        .for_each(|(src, dst)| {
+            assert_eq!(
+                format_text(src, &DEFAULT_CONFIGURATION).unwrap().unwrap(),
+                dst
+            );
+            assert_eq!(
+                format_text(dst, &DEFAULT_CONFIGURATION).unwrap().unwrap(),
+                dst
+            );
+        });
+
+        // a nested list spanning lines expands the list around it
+        let config = Configuration {
+            multi_line_threshold: Some(3),
+            ..DEFAULT_CONFIGURATION
+        };
+        vec![
+            (
+                "x = [['a', 'b', 'c', 'd'], 'e']",
+                "x = [\n  [\n    'a',\n    'b',\n    'c',\n    'd',\n  ],\n  'e',\n]",
+            ),
+            (
+                "x = [[\n'a'], 'e']",
+                "x = [\n  [\n    'a',\n  ],\n  'e',\n]",
+            ),
+            (
+                "x = executable('a', ['a', 'b', 'c', 'd'])",
+                "x = executable('a', [\n  'a',\n  'b',\n  'c',\n  'd',\n])",
+            ),
+            (
+                "x = {'a': ['a', 'b', 'c', 'd'], 'b': 1}",
+                "x = {\n  'a': [\n    'a',\n    'b',\n    'c',\n    'd',\n  ],\n  'b': 1,\n}",
+            ),
+        ]
+        .into_iter()
+        .for_each(|(src, dst)| {
+            assert_eq!(format_text(src, &config).unwrap().unwrap(), dst);
+            assert_eq!(format_text(dst, &config).unwrap().unwrap(), dst);
+        });
+    }
+
+    #[test]
+    fn format_array_fill() {
+        let config = Configuration {
+            line_width: 30,
+            array_layout: ArrayLayout::Fill,
+            ..DEFAULT_CONFIGURATION
+        };
+        vec![
+            (
+                "flags = [\n'-Wall', '-Wextra', '-Wshadow', '-Wundef',\n'-Wformat=2', true, -1]",
+                "flags = [\n  '-Wall', '-Wextra',\n  '-Wshadow', '-Wundef',\n  '-Wformat=2', true, -1,\n]",
+            ),
+            ("x = ['a', 'b']", "x = ['a', 'b']"),
+            ("x = [\n  'a', # c\n  'b',\n]", "x = [\n  'a', # c\n  'b',\n]"),
+            ("x = [\n  a,\n  'b',\n]", "x = [\n  a,\n  'b',\n]"),
+            ("f(\n  'a',\n  'b',\n)", "f(\n  'a',\n  'b',\n)"),
+        ]
+        .into_iter()
+        .for_each(|(src, dst)| {
+            assert_eq!(format_text(src, &config).unwrap().unwrap(), dst);
+            assert_eq!(format_text(dst, &config).unwrap().unwrap(), dst);
+        });
+
+        // arrays on one line are filled when they do not fit
+        let config = Configuration {
+            line_width: 50,
+            ..config
+        };
+        let src = "flags = ['-Wall', '-Wextra', '-Wpedantic', '-Wshadow', '-Wconversion', '-Wformat=2', '-Wundef', '-Werror']";
+        let dst = "flags = [\n  '-Wall', '-Wextra', '-Wpedantic', '-Wshadow',\n  '-Wconversion', '-Wformat=2', '-Wundef',\n  '-Werror',\n]";
+        assert_eq!(format_text(src, &config).unwrap().unwrap(), dst);
+        assert_eq!(format_text(dst, &config).unwrap().unwrap(), dst);
+        let src = "flags = ['-Wall', '-Wextra', '-Wpedantic', '-Wshadow', '-Wconversion', x]";
+        assert_eq!(format_text(src, &config).unwrap().unwrap(), src);
+    }
+    #[test]
+    fn format_tabs() {
+        vec![
+            ("x\t=\t1\t#\tc\t", "x = 1 # c"),
+            ("foo\t(a,\tb)", "foo(a, b)"),
+            (
+                "if\tx\n\tforeach\ta\t:\tb\n\t\ty = 1\n\tendforeach\n\x0c\nendif",
+                "if x\n  foreach a : b\n    y = 1\n  endforeach\n\nendif",
+            ),
+            ("x = [\n\t1,\n\t2,\n]", "x = [\n  1,\n  2,\n]"),
+            ("x = '\ta' + '''\n\tb'''", "x = '\ta' + '''\n\tb'''"),
+            ("if x\n  \n  y = 1\nendif", "if x\n\n  y = 1\nendif"),
+        ]
+        .into_iter()
+        .for_each(|(src, dst)| {
+            assert_eq!(
+                format_text(src, &DEFAULT_CONFIGURATION).unwrap().unwrap(),
+                dst
+            );
+            assert_eq!(
+                format_text(dst, &DEFAULT_CONFIGURATION).unwrap().unwrap(),
+                dst
+            );
+        });
+        assert_eq!(
+            format_text("x = 1\x0b", &DEFAULT_CONFIGURATION),
+            Err(ParseError::UnexpectedSyntax {
+                found: "`\x0b`".into(),
+                expected: "a meson token".into(),
+                line: 1,
+                column: 6,
+            })
+        );
+    }
+
+    #[test]
+    fn format_line_breaks() {
+        vec![
+            ("x = 1 +\n2", "end of line", "`+`", 1, 8),
+            ("x =\n[1]", "end of line", "`=`", 1, 4),
+            ("if a and\nb\nendif", "end of line", "`and`", 1, 9),
+            ("foreach a :\nb\nendforeach", "end of line", "`:`", 1, 12),
+            ("if x\n  y = a. # c\nendif", "comment", "`a.`", 2, 10),
+            ("x = not", "end of file", "`not`", 1, 7),
+        ]
+        .into_iter()
+        .for_each(|(src, found, operator, line, column)| {
+            assert_eq!(
+                format_text(src, &DEFAULT_CONFIGURATION),
+                Err(ParseError::UnexpectedSyntax {
+                    found: found.into(),
+                    expected: format!("an operand after {}", operator),
+                    line,
+                    column,
+                })
+            );
+        });
+
+        // nor begin with one
+        vec![
+            ("x = a\n* b", "`*`", 2, 1),
+            ("x = a\n== b", "`=`", 2, 1),
+            ("x = (a)\n  .b()", "`.`", 2, 3),
+            ("if a\n  x = b\n  or c\nendif", "`or`", 3, 3),
+            ("foreach a : b\n  in c\nendforeach", "`in`", 2, 3),
+            ("x = [1, * 2]", "`*`", 1, 9),
+            ("x = = 1", "`=`", 1, 5),
+        ]
+        .into_iter()
+        .for_each(|(src, operator, line, column)| {
+            assert_eq!(
+                format_text(src, &DEFAULT_CONFIGURATION),
+                Err(ParseError::UnexpectedSyntax {
+                    found: operator.into(),
+                    expected: format!("an operand before {}", operator),
+                    line,
+                    column,
+                })
+            );
+        });
+
+        // a comment inside a list item does not end it
+        vec![
+            ("x = [a # c\n  + b]", "x = [\n  a # c\n    + b,\n]"),
+            (
+                "f(a # c\n - b, x: 1 # d\n * 2)",
+                "f(\n  a # c\n    - b,\n  x: 1 # d\n    * 2,\n)",
+            ),
+            ("x = [a # c\n, b]", "x = [\n  a, # c\n  b,\n]"),
+        ]
+        .into_iter()
+        .for_each(|(src, dst)| {
+            assert_eq!(
+                format_text(src, &DEFAULT_CONFIGURATION).unwrap().unwrap(),
+                dst
+            );
+            assert_eq!(
+                format_text(dst, &DEFAULT_CONFIGURATION).unwrap().unwrap(),
+                dst
+            );
+        });
+
+        // long statements are only broken inside brackets
+        let config = Configuration {
+            line_width: 20,
+            ..DEFAULT_CONFIGURATION
+        };
+        for src in [
+            "x = aaaaaaaa + bbbbbbbb - cccccccc",
+            "if aaaaaaaa and bbbbbbbb or cccccccc\nendif",
+            "foreach a : b\n  x = bbbbbbbb.cccccccc().dddddddd()\nendforeach",
+            "x = -(aaaaaaaa + bbbbbbbb) * cccccccc",
+        ] {
+            let dst = format_text(src, &config).unwrap().unwrap();
+            assert!(dst.lines().count() > src.lines().count(), "{}", dst);
+            assert!(crate::ast::parse_file(&dst).is_ok(), "{}", dst);
+            // the added lines begin inside brackets
+            let depths = crate::ast::line_depths(&dst).unwrap();
+            let outside = depths.iter().filter(|d| **d == 0).count();
+            assert_eq!(outside, src.lines().count(), "{}", dst);
+            assert_eq!(format_text(&dst, &config).unwrap().unwrap(), dst);
+        }
+    }
+
     #[test]
     fn format_variables() {
         vec![
@@ -71,7 +667,7 @@ mod tests {
         vec![
             ("''", "''"),
             ("''''''", "''''''"),
-            ("''s''s''", "'' s '' s ''"),
+            ("''+s+''", "'' + s + ''"),
             ("'''s'''", "'''s'''"),
             ("'some \\'string\\''", "'some \\'string\\''"),
             ("'#some comment string'", "'#some comment string'"),
@@ -89,6 +685,87 @@ joined = 'C:\\foo\\bar' / 'D:\\builddir' # => D:/builddir
             ),
             ("s=f'int: @n@, string: @m@'", "s = f'int: @n@, string: @m@'"),
             ("' '.join(list)", "' '.join(list)"),
+            ("x=['a\\\\','b']", "x = ['a\\\\', 'b']"),
+        ]
+        .into_iter()
+        .for_each(|(src, dst)| {
+            assert_eq!(
+                format_text(src, &DEFAULT_CONFIGURATION).unwrap().unwrap(),
+                dst
+            );
+            assert_eq!(
+                format_text(dst, &DEFAULT_CONFIGURATION).unwrap().unwrap(),
+                dst
+            );
+        });
+    }
+
+    #[test]
+    fn format_multiline_strings() {
+        let config = Configuration {
+            line_width: 20,
+            normalize_strings: true,
+            align_assignments: true,
+            comment_wrap: true,
+            ..DEFAULT_CONFIGURATION
+        };
+        vec![
+            (
+                "if x\nforeach a:b\ny=f'''@a@\n  b  #c\n'''\nendforeach\nendif",
+                "if x\n  foreach a : b\n    y = f'''@a@\n  b  #c\n'''\n  endforeach\nendif",
+            ),
+            (
+                "foo(\n'''\n a''',\nk:f'''b\n''')",
+                "foo(\n  '''\n a''',\n  k: f'''b\n''',\n)",
+            ),
+            ("x='''a\\'''\nyy=-f'a'", "x  = '''a\\'''\nyy = -f'a'"),
+            (
+                "x = 'aaaaaaaa' + '''\nb''' + 'cccccccc'",
+                "x = 'aaaaaaaa' + '''\nb''' + 'cccccccc'",
+            ),
+        ]
+        .into_iter()
+        .for_each(|(src, dst)| {
+            assert_eq!(format_text(src, &config).unwrap().unwrap(), dst);
+            assert_eq!(format_text(dst, &config).unwrap().unwrap(), dst);
+        });
+    }
+
+    #[test]
+    fn format_normalized_strings() {
+        let config = Configuration {
+            normalize_strings: true,
+            collapse_multiline_strings: true,
+            ..DEFAULT_CONFIGURATION
+        };
+        vec![
+            ("x='a\\x41\\u00e9'", "x = 'aAé'"),
+            ("x=f'@a@\\x41\\n'", "x = f'@a@A\\n'"),
+            ("x='a\\nb'+'c'", "x = '''a\nb''' + 'c'"),
+            ("x='''it's'''", "x = 'it\\'s'"),
+            ("x='\\d+\\\\'", "x = '\\d+\\\\'"),
+        ]
+        .into_iter()
+        .for_each(|(src, dst)| {
+            assert_eq!(format_text(src, &config).unwrap().unwrap(), dst);
+            assert_eq!(format_text(dst, &config).unwrap().unwrap(), dst);
+        });
+    }
+
+    #[test]
+    fn format_unary_operators() {
+        vec![
+            ("x=a +b", "x = a + b"),
+            ("x=a--b", "x = a - -b"),
+            ("x=a - - b", "x = a - -b"),
+            ("x=- 1+-(a)", "x = -1 + -(a)"),
+            ("x=f(x)-a[0]-'s'", "x = f(x) - a[0] - 's'"),
+            ("foo(-a,b:- 1)", "foo(-a, b: -1)"),
+            ("x=c?-1:-2", "x = c ? -1 : -2"),
+            (
+                "if not(a or b) and-x\nendif",
+                "if not (a or b) and -x\nendif",
+            ),
         ]
         .into_iter()
         .for_each(|(src, dst)| {
@@ -101,6 +778,143 @@ joined = 'C:\\foo\\bar' / 'D:\\builddir' # => D:/builddir
                 dst
             );
         });
+
+        // meson has no unary plus
+        vec![
+            ("x = +1", 1, 5),
+            ("x = c ? 1 : +2", 1, 13),
+            ("foo(a, + b)", 1, 8),
+            ("x = a + +b", 1, 9),
+        ]
+        .into_iter()
+        .for_each(|(src, line, column)| {
+            assert_eq!(
+                format_text(src, &DEFAULT_CONFIGURATION),
+                Err(ParseError::UnexpectedSyntax {
+                    found: "`+`".into(),
+                    expected: "an operand before `+`".into(),
+                    line,
+                    column,
+                })
+            );
+            assert!(crate::ast::parse_file(src).is_err(), "{}", src);
+        });
+    }
+
+    #[test]
+    fn format_redundant_parens() {
+        let config = Configuration {
+            remove_redundant_parens: true,
+            ..DEFAULT_CONFIGURATION
+        };
+        vec![
+            ("x=(a+b)", "x = a + b"),
+            ("x=(a+b)*-(c)", "x = (a + b) * -c"),
+            (
+                "if (a) or (b and c)\nelif not(d)\nendif",
+                "if a or b and c\nelif not d\nendif",
+            ),
+            ("foo((a), [(b)])", "foo(a, [b])"),
+            ("x=(a#c\n+b)", "x = (\n  a # c\n  + b\n)"),
+        ]
+        .into_iter()
+        .for_each(|(src, dst)| {
+            assert_eq!(format_text(src, &config).unwrap().unwrap(), dst);
+            assert_eq!(format_text(dst, &config).unwrap().unwrap(), dst);
+        });
+    }
+
+    #[test]
+    fn format_aligned() {
+        let config = Configuration {
+            align_assignments: true,
+            align_trailing_comments: true,
+            ..DEFAULT_CONFIGURATION
+        };
+        vec![
+            (
+                "inc=include_directories('.')#a\nfoo_dep+=dependency('foo')  #b\n",
+                "inc      = include_directories('.') # a\nfoo_dep += dependency('foo')        # b\n",
+            ),
+            (
+                "a=1\nbb=[\n1,#x\n22,#y\n]\nccc=3\n",
+                "a = 1\nbb = [\n  1,  # x\n  22, # y\n]\nccc = 3\n",
+            ),
+        ]
+        .into_iter()
+        .for_each(|(src, dst)| {
+            assert_eq!(format_text(src, &config).unwrap().unwrap(), dst);
+            assert_eq!(format_text(dst, &config).unwrap().unwrap(), dst);
+        });
+    }
+
+    #[test]
+    fn format_long_expressions() {
+        let config = Configuration {
+            line_width: 40,
+            ..DEFAULT_CONFIGURATION
+        };
+        vec![
+            ("x=( a+b )*c", "x = (a + b) * c"),
+            ("x=-(a)", "x = -(a)"),
+            ("x=(a#c\n+ b)", "x = (\n  a # c\n  + b\n)"),
+            (
+                "src+=files('aaaa.c')+files('bbbb.c')+files('cccc.c')",
+                "src += (\n  files('aaaa.c')\n  + files('bbbb.c')\n  + files('cccc.c')\n)",
+            ),
+            (
+                "if aaaaaaaaaaaaaaaa and bbbbbbbbbbbbbbbbbbbbbb\nendif",
+                "if (\n  aaaaaaaaaaaaaaaa\n  and bbbbbbbbbbbbbbbbbbbbbb\n)\nendif",
+            ),
+            (
+                "foreach x:y\n  z=aaaaaaaaaaaaaaaaaaaa+bbbbbbbbbbbbbbbbbbbbb\nendforeach",
+                "foreach x : y\n  z = (\n    aaaaaaaaaaaaaaaaaaaa\n    + bbbbbbbbbbbbbbbbbbbbb\n  )\nendforeach",
+            ),
+            (
+                "d=dependency('foo').partial_dependency(compile_args:true).as_system()",
+                "d = (\n  dependency('foo')\n  .partial_dependency(\n    compile_args: true,\n  )\n  .as_system()\n)",
+            ),
+            (
+                "x=f(aaaaaaaaaaaaaaaa+bbbbbbbbbbbbbbbbbbbbbb)",
+                "x = f(\n  aaaaaaaaaaaaaaaa\n    + bbbbbbbbbbbbbbbbbbbbbb,\n)",
+            ),
+        ]
+        .into_iter()
+        .for_each(|(src, dst)| {
+            assert_eq!(format_text(src, &config).unwrap().unwrap(), dst);
+            assert_eq!(format_text(dst, &config).unwrap().unwrap(), dst);
+        });
+
+        // calls of a broken chain still too long break their arguments
+        let config = Configuration {
+            line_width: 80,
+            ..config
+        };
+        let src = "if a\n  foo_dep = subproject('foo', default_options: ['tests=false', 'docs=disabled', 'werror=false']).get_variable('foo_dep').as_system()\nendif";
+        let dst = "if a\n  foo_dep = (\n    subproject('foo',\n      default_options: ['tests=false', 'docs=disabled', 'werror=false'],\n    )\n    .get_variable('foo_dep')\n    .as_system()\n  )\nendif";
+        assert_eq!(format_text(src, &config).unwrap().unwrap(), dst);
+        assert_eq!(format_text(dst, &config).unwrap().unwrap(), dst);
+
+        let config = Configuration {
+            line_width: 60,
+            ..config
+        };
+        let dst = "x = executable('a',\n  sources: files('aaaaaaaa.c')\n    + files('bbbbbbbbbbbbbbbb.c')\n    + files('cccccccccccc.c'),\n  install: true,\n)";
+        let src = "x = executable('a', sources: files('aaaaaaaa.c') + files('bbbbbbbbbbbbbbbb.c') + files('cccccccccccc.c'), install: true)";
+        assert_eq!(format_text(src, &config).unwrap().unwrap(), dst);
+        assert_eq!(format_text(dst, &config).unwrap().unwrap(), dst);
+
+        let config = Configuration {
+            line_width: 40,
+            operator_position: crate::configuration::OperatorPosition::SameLine,
+            ..config
+        };
+        assert_eq!(
+            format_text("x=aaaaaaaaaaaaaaaaaaaaaa or bbbbbbbbbbbbbbbbbbbb", &config)
+                .unwrap()
+                .unwrap(),
+            "x = (\n  aaaaaaaaaaaaaaaaaaaaaa or\n  bbbbbbbbbbbbbbbbbbbb\n)"
+        );
     }
 
     #[test]
@@ -148,7 +962,7 @@ joined = 'C:\\foo\\bar' / 'D:\\builddir' # => D:/builddir
     fn format_dictionary() {
         vec![
             ("{}", "{}"),
-            ("{{}\n,[]}", "{\n  {},\n  [],\n}"),
+            ("{'a':{}\n,'b':[]}", "{\n  'a': {},\n  'b': [],\n}"),
             (
                 "my_dict={'foo':42,'bar':'baz'}",
                 "my_dict = {'foo': 42, 'bar': 'baz'}",
@@ -174,7 +988,7 @@ joined = 'C:\\foo\\bar' / 'D:\\builddir' # => D:/builddir
     #[test]
     fn format_argument() {
         vec![
-            ("()", "()"),
+            ("f()", "f()"),
             (
                 "executable('progname','prog.c')",
                 "executable('progname', 'prog.c')",
@@ -280,7 +1094,7 @@ if a and b
   if not e
     # do something
   endif
-  if not(f or g)
+  if not (f or g)
     # do something
   endif
 endif
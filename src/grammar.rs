@@ -1,4 +1,23 @@
-/// is identifier, literal
+/// char of an identifier, or of an integer literal after its first digit
 pub fn is_identifier(c: char) -> bool {
     c.is_ascii_alphanumeric() || c == '_'
 }
+
+/// char of an operator or punctuation
+pub fn is_symbol(c: char) -> bool {
+    "+-*/%=!<>()[]{},:.?".contains(c)
+}
+
+/// keywords which may be followed by an expression
+pub fn is_keyword(word: &str) -> bool {
+    matches!(word, "not" | "and" | "or" | "in" | "if" | "elif")
+}
+
+/// words which can not name a variable
+pub fn is_reserved(word: &str) -> bool {
+    is_keyword(word)
+        || matches!(
+            word,
+            "true" | "false" | "else" | "endif" | "foreach" | "endforeach" | "break" | "continue"
+        )
+}
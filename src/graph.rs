@@ -0,0 +1,516 @@
+use crate::ast::{self, AssignOperator, BinaryOperator, Expr, ExprKind, Statement, Visitor};
+use crate::parser::ParseError;
+use crate::project::Project;
+use serde::Serialize;
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// functions declaring targets
+const TARGET_FUNCTIONS: [&str; 8] = [
+    "executable",
+    "library",
+    "shared_library",
+    "static_library",
+    "custom_target",
+    "run_target",
+    "test",
+    "benchmark",
+];
+
+/// targets declared by build files, read without configuring the project
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct BuildGraph {
+    pub targets: Vec<Target>,
+}
+
+impl BuildGraph {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Target {
+    /// the function declaring the target, like `executable`
+    pub kind: String,
+    /// `None` when the name is not a string literal
+    pub name: Option<String>,
+    /// variable the target is assigned to
+    pub variable: Option<String>,
+    /// build file relative to the project root
+    pub file: PathBuf,
+    pub line: usize,
+    /// string literals and `files()` arguments of the sources, relative to
+    /// the directory of `file`
+    pub sources: Vec<String>,
+    pub dependencies: Vec<Reference>,
+    pub link_with: Vec<Reference>,
+    /// the executable run by `test()` and `benchmark()`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub executable: Option<Reference>,
+}
+
+/// an object a target refers to
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Reference {
+    /// variable holding the object
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub variable: Option<String>,
+    /// the function creating the object, like `dependency` or `library`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    /// name of the dependency or target
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+/// the targets of one build file, `subdir()` calls are not followed
+pub fn build_graph(file_text: &str) -> Result<BuildGraph, ParseError> {
+    let file = ast::parse_file(file_text)?;
+    let mut builder = GraphBuilder::new(None);
+    ast::walk_statements(&mut builder, &file.statements);
+    Ok(BuildGraph {
+        targets: builder.targets,
+    })
+}
+
+/// the targets of a project, read from the top level `meson.build` through
+/// `subdir()` calls like meson does, so variables of a directory are known in
+/// the directories read after it. Files which can not be read or parsed are
+/// skipped.
+pub fn project_graph(project: &Project) -> BuildGraph {
+    let mut builder = GraphBuilder::new(Some(&project.root));
+    builder.read_file(PathBuf::from("meson.build"));
+    BuildGraph {
+        targets: builder.targets,
+    }
+}
+
+/// what is known about the value of an expression
+#[derive(Debug, Clone)]
+enum Value {
+    Strings(Vec<String>),
+    Objects(Vec<Reference>),
+    Unknown,
+}
+
+struct GraphBuilder<'a> {
+    root: Option<&'a Path>,
+    /// the file being read, relative to `root`
+    file: PathBuf,
+    /// files read, meson reads each directory once
+    read: HashSet<PathBuf>,
+    variables: HashMap<String, Value>,
+    targets: Vec<Target>,
+}
+
+impl<'a> GraphBuilder<'a> {
+    fn new(root: Option<&'a Path>) -> Self {
+        Self {
+            root,
+            file: PathBuf::from("meson.build"),
+            read: HashSet::new(),
+            variables: HashMap::new(),
+            targets: vec![],
+        }
+    }
+
+    fn read_file(&mut self, path: PathBuf) {
+        let root = match self.root {
+            Some(root) => root,
+            None => return,
+        };
+        if !self.read.insert(path.clone()) {
+            return;
+        }
+        let file = match fs::read_to_string(root.join(&path)) {
+            Ok(text) => match ast::parse_file(&text) {
+                Ok(file) => file,
+                Err(_) => return,
+            },
+            Err(_) => return,
+        };
+        let parent = std::mem::replace(&mut self.file, path);
+        ast::walk_statements(self, &file.statements);
+        self.file = parent;
+    }
+
+    fn eval(&mut self, expr: &Expr) -> Value {
+        match &expr.kind {
+            ExprKind::String(s) => Value::Strings(vec![s.value.clone()]),
+            ExprKind::Identifier(variable) => match self.variables.get(variable) {
+                // the object is named by the variable
+                Some(Value::Objects(objects)) if objects.len() == 1 => {
+                    Value::Objects(vec![Reference {
+                        variable: Some(variable.clone()),
+                        ..objects[0].clone()
+                    }])
+                }
+                Some(value) => value.clone(),
+                None => Value::Unknown,
+            },
+            ExprKind::Array(items) => {
+                let values = items.iter().map(|e| self.eval(e)).collect::<Vec<_>>();
+                values.into_iter().fold(Value::Strings(vec![]), join)
+            }
+            ExprKind::Parenthesized(inner) => self.eval(inner),
+            ExprKind::Binary {
+                operator: BinaryOperator::Add,
+                left,
+                right,
+            } => {
+                let left = self.eval(left);
+                let right = self.eval(right);
+                join(left, right)
+            }
+            ExprKind::Call { name, arguments } => {
+                let values = arguments
+                    .positional
+                    .iter()
+                    .map(|e| self.eval(e))
+                    .collect::<Vec<_>>();
+                // keyword arguments of targets are read by `declare_target`
+                if !TARGET_FUNCTIONS.contains(&name.name.as_str()) {
+                    for k in &arguments.keywords {
+                        self.eval(&k.value);
+                    }
+                }
+                let first = values.first().and_then(single_string);
+                match name.name.as_str() {
+                    "files" => values.into_iter().fold(Value::Strings(vec![]), join),
+                    "subdir" => {
+                        if let Some(dir) = first {
+                            let parent = self.file.parent().unwrap_or_else(|| Path::new(""));
+                            self.read_file(parent.join(dir).join("meson.build"));
+                        }
+                        Value::Unknown
+                    }
+                    "dependency" | "declare_dependency" => Value::Objects(vec![Reference {
+                        variable: None,
+                        kind: Some(name.name.clone()),
+                        name: first,
+                    }]),
+                    kind if TARGET_FUNCTIONS.contains(&kind) => {
+                        self.declare_target(expr, kind, values)
+                    }
+                    _ => Value::Unknown,
+                }
+            }
+            ExprKind::MethodCall {
+                object, arguments, ..
+            } => {
+                self.eval(object);
+                for e in &arguments.positional {
+                    self.eval(e);
+                }
+                for k in &arguments.keywords {
+                    self.eval(&k.value);
+                }
+                Value::Unknown
+            }
+            ExprKind::Dictionary(items) => {
+                for (_, v) in items {
+                    self.eval(v);
+                }
+                Value::Unknown
+            }
+            ExprKind::Index { object, index } => {
+                self.eval(object);
+                self.eval(index);
+                Value::Unknown
+            }
+            ExprKind::Unary { operand, .. } => {
+                self.eval(operand);
+                Value::Unknown
+            }
+            ExprKind::Binary { left, right, .. } => {
+                self.eval(left);
+                self.eval(right);
+                Value::Unknown
+            }
+            ExprKind::Ternary {
+                condition,
+                if_true,
+                if_false,
+            } => {
+                for e in [condition, if_true, if_false] {
+                    self.eval(e);
+                }
+                Value::Unknown
+            }
+            ExprKind::Bool(_) | ExprKind::Number(_) => Value::Unknown,
+        }
+    }
+
+    /// record the target declared by `expr`, `values` of its positional
+    /// arguments
+    fn declare_target(&mut self, expr: &Expr, kind: &str, values: Vec<Value>) -> Value {
+        let arguments = match &expr.kind {
+            ExprKind::Call { arguments, .. } => arguments,
+            _ => unreachable!(),
+        };
+        let name = values.first().and_then(single_string);
+        let mut sources = vec![];
+        let mut executable = None;
+        match kind {
+            "custom_target" | "run_target" => {}
+            "test" | "benchmark" => {
+                executable = match (values.get(1), arguments.positional.get(1)) {
+                    (Some(Value::Objects(objects)), _) => objects.first().cloned(),
+                    (_, Some(e)) => e.kind.as_identifier().map(|variable| Reference {
+                        variable: Some(variable.to_string()),
+                        ..Reference::default()
+                    }),
+                    _ => None,
+                };
+            }
+            _ => sources.extend(values.into_iter().skip(1).flat_map(strings)),
+        }
+        let mut dependencies = vec![];
+        let mut link_with = vec![];
+        for k in &arguments.keywords {
+            match k.name.name.as_str() {
+                "sources" | "input" => sources.extend(strings(self.eval(&k.value))),
+                "dependencies" => dependencies = self.references(&k.value),
+                "link_with" => link_with = self.references(&k.value),
+                _ => {
+                    self.eval(&k.value);
+                }
+            }
+        }
+        self.targets.push(Target {
+            kind: kind.to_string(),
+            name: name.clone(),
+            variable: None,
+            file: self.file.clone(),
+            line: expr.span.start.line,
+            sources,
+            dependencies,
+            link_with,
+            executable,
+        });
+        Value::Objects(vec![Reference {
+            variable: None,
+            kind: Some(kind.to_string()),
+            name,
+        }])
+    }
+
+    /// objects `expr` refers to, with the variables naming them
+    fn references(&mut self, expr: &Expr) -> Vec<Reference> {
+        match &expr.kind {
+            ExprKind::Identifier(variable) => match self.eval(expr) {
+                Value::Objects(objects) => objects,
+                // not known to hold an object
+                _ => vec![Reference {
+                    variable: Some(variable.clone()),
+                    ..Reference::default()
+                }],
+            },
+            ExprKind::Array(items) => items.iter().flat_map(|e| self.references(e)).collect(),
+            _ => match self.eval(expr) {
+                Value::Objects(objects) => objects,
+                _ => vec![],
+            },
+        }
+    }
+}
+
+impl Visitor for GraphBuilder<'_> {
+    fn visit_statement(&mut self, statement: &Statement) -> bool {
+        match statement {
+            Statement::Expression(expr) => {
+                self.eval(expr);
+                false
+            }
+            Statement::Assignment {
+                target,
+                operator,
+                value,
+                ..
+            } => {
+                let declares = matches!(
+                    &value.kind,
+                    ExprKind::Call { name, .. } if TARGET_FUNCTIONS.contains(&name.name.as_str())
+                );
+                let mut value = self.eval(value);
+                // the target is declared after the ones in its arguments
+                if let Some(declared) = self.targets.last_mut().filter(|_| declares) {
+                    declared.variable = Some(target.name.clone());
+                }
+                if *operator == AssignOperator::AddAssign {
+                    let old = self
+                        .variables
+                        .remove(&target.name)
+                        .unwrap_or(Value::Unknown);
+                    value = join(old, value);
+                }
+                self.variables.insert(target.name.clone(), value);
+                false
+            }
+            Statement::Foreach { variables, .. } => {
+                for variable in variables {
+                    self.variables.insert(variable.name.clone(), Value::Unknown);
+                }
+                true
+            }
+            _ => true,
+        }
+    }
+}
+
+/// `left + right`
+fn join(left: Value, right: Value) -> Value {
+    match (left, right) {
+        (Value::Strings(mut a), Value::Strings(b)) => {
+            a.extend(b);
+            Value::Strings(a)
+        }
+        (Value::Objects(mut a), Value::Objects(b)) => {
+            a.extend(b);
+            Value::Objects(a)
+        }
+        // objects added to an empty array
+        (Value::Strings(a), Value::Objects(b)) | (Value::Objects(b), Value::Strings(a))
+            if a.is_empty() =>
+        {
+            Value::Objects(b)
+        }
+        _ => Value::Unknown,
+    }
+}
+
+fn strings(value: Value) -> Vec<String> {
+    match value {
+        Value::Strings(strings) => strings,
+        _ => vec![],
+    }
+}
+
+fn single_string(value: &Value) -> Option<String> {
+    match value {
+        Value::Strings(strings) if strings.len() == 1 => Some(strings[0].clone()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::discover_project;
+
+    #[test]
+    fn extract_targets() {
+        let text = "zlib = dependency('zlib')
+common = files('a.c', 'b.c')
+lib = static_library('common', common + ['c.c'], dependencies: zlib)
+deps = [zlib, declare_dependency(link_with: lib)]
+app = executable('app', 'main.c', sources: files('d.c'), dependencies: deps, link_with: [lib])
+test('app-test', app)
+foreach n : ['x']
+  benchmark(n, executable('bench', src))
+endforeach
+";
+        let graph = build_graph(text).unwrap();
+        let summary = graph
+            .targets
+            .iter()
+            .map(|t| {
+                (
+                    t.kind.as_str(),
+                    t.name.as_deref(),
+                    t.variable.as_deref(),
+                    t.line,
+                    t.sources.join(" "),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                (
+                    "static_library",
+                    Some("common"),
+                    Some("lib"),
+                    3,
+                    "a.c b.c c.c".into()
+                ),
+                (
+                    "executable",
+                    Some("app"),
+                    Some("app"),
+                    5,
+                    "main.c d.c".into()
+                ),
+                ("test", Some("app-test"), None, 6, "".into()),
+                ("executable", Some("bench"), None, 8, "".into()),
+                ("benchmark", None, None, 8, "".into()),
+            ]
+        );
+
+        let zlib = Reference {
+            variable: Some("zlib".into()),
+            kind: Some("dependency".into()),
+            name: Some("zlib".into()),
+        };
+        let lib = Reference {
+            variable: Some("lib".into()),
+            kind: Some("static_library".into()),
+            name: Some("common".into()),
+        };
+        assert_eq!(graph.targets[0].dependencies.len(), 1);
+        assert_eq!(graph.targets[0].dependencies[0], zlib);
+        let app = &graph.targets[1];
+        assert_eq!(app.dependencies.len(), 2);
+        assert_eq!(app.dependencies[0], zlib);
+        assert_eq!(
+            app.dependencies[1].kind.as_deref(),
+            Some("declare_dependency")
+        );
+        assert_eq!(app.link_with, [lib]);
+        assert_eq!(
+            graph.targets[2]
+                .executable
+                .as_ref()
+                .unwrap()
+                .variable
+                .as_deref(),
+            Some("app")
+        );
+        assert_eq!(
+            graph.targets[4]
+                .executable
+                .as_ref()
+                .unwrap()
+                .name
+                .as_deref(),
+            Some("bench")
+        );
+
+        let json = serde_json::to_value(&graph).unwrap();
+        assert_eq!(json["targets"][1]["linkWith"][0]["name"], "common");
+        assert_eq!(json["targets"][2]["executable"]["variable"], "app");
+        assert!(json["targets"][0].get("executable").is_none());
+    }
+
+    #[test]
+    fn extract_project_targets() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/project");
+        let graph = project_graph(&discover_project(&root));
+        let targets = graph
+            .targets
+            .iter()
+            .map(|t| (t.file.to_str().unwrap(), t.name.as_deref().unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            targets,
+            [("src/meson.build", "demo"), ("docs/meson.build", "gen")]
+        );
+        // variables of directories read before
+        assert_eq!(graph.targets[1].link_with[0].name.as_deref(), Some("demo"));
+    }
+}
@@ -0,0 +1,326 @@
+use crate::ast::{self, Expr, ExprKind, Statement, Visitor};
+use crate::parser::ParseError;
+use crate::project::Project;
+use serde::Serialize;
+use std::{
+    collections::HashSet,
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// functions using third-party code
+const INVENTORY_FUNCTIONS: [&str; 3] = ["dependency", "subproject", "find_program"];
+
+/// `dependency()`, `subproject()` and `find_program()` calls of build files
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Inventory {
+    pub entries: Vec<InventoryEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InventoryEntry {
+    /// the function called, like `dependency`
+    pub kind: String,
+    /// string arguments, the names tried in turn
+    pub names: Vec<String>,
+    /// constraints of `version:`
+    pub version: Vec<String>,
+    /// `required:` as written, `None` when it is not given
+    pub required: Option<String>,
+    /// strings of `fallback:`, the subproject and its variable
+    pub fallback: Vec<String>,
+    pub method: Option<String>,
+    /// build file relative to the project root
+    pub file: PathBuf,
+    pub line: usize,
+    /// conditions of the enclosing `if` branches as written, `not (...)` for
+    /// the ones of earlier branches
+    pub conditions: Vec<String>,
+}
+
+impl Inventory {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    /// one line per entry with aligned columns
+    pub fn to_table(&self) -> String {
+        let header = [
+            "FILE",
+            "LINE",
+            "KIND",
+            "NAMES",
+            "VERSION",
+            "REQUIRED",
+            "FALLBACK",
+            "METHOD",
+            "CONDITIONS",
+        ]
+        .map(String::from);
+        let mut rows = vec![header];
+        for entry in &self.entries {
+            rows.push([
+                entry.file.display().to_string(),
+                entry.line.to_string(),
+                entry.kind.clone(),
+                entry.names.join(", "),
+                entry.version.join(", "),
+                entry.required.clone().unwrap_or_default(),
+                entry.fallback.join(", "),
+                entry.method.clone().unwrap_or_default(),
+                entry.conditions.join(" and "),
+            ]);
+        }
+        let widths = (0..9)
+            .map(|i| rows.iter().map(|r| r[i].chars().count()).max().unwrap_or(0))
+            .collect::<Vec<_>>();
+        let mut table = String::new();
+        for row in rows {
+            let mut line = String::new();
+            for (cell, width) in row.iter().zip(&widths) {
+                write!(line, "{:w$}  ", cell, w = width).unwrap();
+            }
+            table.push_str(line.trim_end());
+            table.push('\n');
+        }
+        table
+    }
+}
+
+/// the inventory of one build file, `subdir()` calls are not followed
+pub fn inventory(file_text: &str) -> Result<Inventory, ParseError> {
+    let file = ast::parse_file(file_text)?;
+    let mut builder = InventoryBuilder::new(None);
+    builder.text = file_text.to_string();
+    ast::walk_statements(&mut builder, &file.statements);
+    Ok(Inventory {
+        entries: builder.entries,
+    })
+}
+
+/// the inventory of a project, read from the top level `meson.build` through
+/// `subdir()` calls, whose conditions guard the whole directory. Files which
+/// can not be read or parsed are skipped.
+pub fn project_inventory(project: &Project) -> Inventory {
+    let mut builder = InventoryBuilder::new(Some(&project.root));
+    builder.read_file(PathBuf::from("meson.build"));
+    Inventory {
+        entries: builder.entries,
+    }
+}
+
+struct InventoryBuilder<'a> {
+    root: Option<&'a Path>,
+    /// the file being read, relative to `root`, and its text
+    file: PathBuf,
+    text: String,
+    read: HashSet<PathBuf>,
+    conditions: Vec<String>,
+    entries: Vec<InventoryEntry>,
+}
+
+impl<'a> InventoryBuilder<'a> {
+    fn new(root: Option<&'a Path>) -> Self {
+        Self {
+            root,
+            file: PathBuf::from("meson.build"),
+            text: String::new(),
+            read: HashSet::new(),
+            conditions: vec![],
+            entries: vec![],
+        }
+    }
+
+    fn read_file(&mut self, path: PathBuf) {
+        let root = match self.root {
+            Some(root) => root,
+            None => return,
+        };
+        if !self.read.insert(path.clone()) {
+            return;
+        }
+        let text = match fs::read_to_string(root.join(&path)) {
+            Ok(text) => text,
+            Err(_) => return,
+        };
+        let file = match ast::parse_file(&text) {
+            Ok(file) => file,
+            Err(_) => return,
+        };
+        let parent_file = std::mem::replace(&mut self.file, path);
+        let parent_text = std::mem::replace(&mut self.text, text);
+        ast::walk_statements(self, &file.statements);
+        self.file = parent_file;
+        self.text = parent_text;
+    }
+
+    fn source(&self, expr: &Expr) -> String {
+        expr.span.text(&self.text).to_string()
+    }
+
+    /// `not` of a condition of an earlier branch
+    fn negate(&self, condition: &Expr) -> String {
+        match condition.kind {
+            ExprKind::Identifier(_)
+            | ExprKind::Call { .. }
+            | ExprKind::MethodCall { .. }
+            | ExprKind::Index { .. }
+            | ExprKind::Parenthesized(_) => format!("not {}", self.source(condition)),
+            _ => format!("not ({})", self.source(condition)),
+        }
+    }
+}
+
+/// string literals of a string or an array of strings
+fn strings(expr: &Expr) -> Vec<String> {
+    match &expr.kind {
+        ExprKind::String(s) => vec![s.value.clone()],
+        ExprKind::Array(items) => items.iter().flat_map(strings).collect(),
+        _ => vec![],
+    }
+}
+
+impl Visitor for InventoryBuilder<'_> {
+    fn visit_statement(&mut self, statement: &Statement) -> bool {
+        let (branches, else_block) = match statement {
+            Statement::If {
+                branches,
+                else_block,
+                ..
+            } => (branches, else_block),
+            _ => return true,
+        };
+        let depth = self.conditions.len();
+        for (condition, block) in branches {
+            ast::walk_expr(self, condition);
+            self.conditions.push(self.source(condition));
+            ast::walk_statements(self, block);
+            self.conditions.pop();
+            // later branches run when this one does not
+            self.conditions.push(self.negate(condition));
+        }
+        if let Some(block) = else_block {
+            ast::walk_statements(self, block);
+        }
+        self.conditions.truncate(depth);
+        false
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) -> bool {
+        let (name, arguments) = match &expr.kind {
+            ExprKind::Call { name, arguments } => (name.name.as_str(), arguments),
+            _ => return true,
+        };
+        if name == "subdir" {
+            if let Some(dir) = arguments.positional.first().and_then(|e| e.kind.as_str()) {
+                let parent = self.file.parent().unwrap_or_else(|| Path::new(""));
+                let path = parent.join(dir).join("meson.build");
+                self.read_file(path);
+            }
+        }
+        if !INVENTORY_FUNCTIONS.contains(&name) {
+            return true;
+        }
+        let keyword = |key: &str| arguments.keyword(key);
+        let version = match keyword("version") {
+            Some(e) if strings(e).is_empty() => vec![self.source(e)],
+            Some(e) => strings(e),
+            None => vec![],
+        };
+        self.entries.push(InventoryEntry {
+            kind: name.to_string(),
+            names: arguments.positional.iter().flat_map(strings).collect(),
+            version,
+            required: keyword("required").map(|e| self.source(e)),
+            fallback: keyword("fallback").map(strings).unwrap_or_default(),
+            method: keyword("method").and_then(|e| e.kind.as_str().map(String::from)),
+            file: self.file.clone(),
+            line: expr.span.start.line,
+            conditions: self.conditions.clone(),
+        });
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::discover_project;
+
+    #[test]
+    fn list_calls() {
+        let text = "zlib = dependency('zlib', 'z', version: ['>=1.2', '<2'], required: false)
+if get_option('gui')
+  gtk = dependency('gtk4', method: 'pkg-config', fallback: ['gtk', 'gtk_dep'])
+elif host_machine.system() == 'windows'
+  win = find_program('rc', required: get_option('rc'))
+else
+  sub = subproject('tui', version: tui_version)
+endif
+";
+        let inventory = inventory(text).unwrap();
+        let entries = &inventory.entries;
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries[0].names, ["zlib", "z"]);
+        assert_eq!(entries[0].version, [">=1.2", "<2"]);
+        assert_eq!(entries[0].required.as_deref(), Some("false"));
+        assert!(entries[0].conditions.is_empty());
+        assert_eq!(entries[1].method.as_deref(), Some("pkg-config"));
+        assert_eq!(entries[1].fallback, ["gtk", "gtk_dep"]);
+        assert_eq!(entries[1].conditions, ["get_option('gui')"]);
+        assert_eq!(entries[2].kind, "find_program");
+        assert_eq!(entries[2].line, 5);
+        assert_eq!(entries[2].required.as_deref(), Some("get_option('rc')"));
+        assert_eq!(
+            entries[2].conditions,
+            [
+                "not get_option('gui')",
+                "host_machine.system() == 'windows'"
+            ]
+        );
+        assert_eq!(entries[3].version, ["tui_version"]);
+        assert_eq!(
+            entries[3].conditions,
+            [
+                "not get_option('gui')",
+                "not (host_machine.system() == 'windows')"
+            ]
+        );
+
+        let table = inventory.to_table();
+        let lines = table.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 5);
+        assert!(lines[0].starts_with("FILE         LINE  KIND"));
+        assert!(lines[1].starts_with("meson.build  1     dependency"));
+
+        let json = serde_json::to_value(&inventory).unwrap();
+        assert_eq!(json["entries"][1]["fallback"][1], "gtk_dep");
+        assert_eq!(json["entries"][0]["method"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn list_project_calls() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/project");
+        let inventory = project_inventory(&discover_project(&root));
+        let entries = inventory
+            .entries
+            .iter()
+            .map(|e| {
+                (
+                    e.file.to_str().unwrap(),
+                    e.kind.as_str(),
+                    e.conditions.clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            entries,
+            [(
+                "docs/meson.build",
+                "find_program",
+                vec!["get_option('docs')".to_string()]
+            )]
+        );
+    }
+}
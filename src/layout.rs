@@ -0,0 +1,755 @@
+use crate::ast::{self, BinaryOperator, Expr, ExprKind, Span, Statement, Visitor};
+use crate::configuration::{Configuration, ListKind, OperatorPosition};
+
+/// Break a formatted statement which is longer than the line width.
+///
+/// `indent` is the indentation of the statement and `prefix` the width of the
+/// text before it on its first line, like `if `. Returns `None` when the
+/// statement is kept as it is.
+pub fn wrap_statement(
+    line: &str,
+    indent: usize,
+    prefix: usize,
+    config: &Configuration,
+) -> Option<String> {
+    let width = config.line_width as usize;
+    let fits = line.lines().enumerate().all(|(i, l)| {
+        let first = if i == 0 { indent + prefix } else { 0 };
+        first + l.chars().count() <= width
+    });
+    // a broken method chain comes back from the formatter joined on fewer
+    // lines, which may fit
+    if fits && !line.contains('\n') {
+        return None;
+    }
+
+    let (keyword, code) = match line.strip_prefix("elif ") {
+        Some(code) => ("elif ", code),
+        None => ("", line),
+    };
+    let file = ast::parse_file(code).ok()?;
+    let expr = match file.statements.as_slice() {
+        [Statement::Expression(expr)] | [Statement::Assignment { value: expr, .. }] => expr,
+        _ => return None,
+    };
+    let wrapper = Wrapper {
+        code,
+        config,
+        indent,
+        first_column: indent + prefix + keyword.len(),
+    };
+    let column = wrapper.column(expr);
+    let wrapped = wrapper.wrap(expr, indent, column, false)?;
+    Some(format!(
+        "{}{}{}{}",
+        keyword,
+        &code[..expr.span.start.offset],
+        wrapped,
+        &code[expr.span.end.offset..]
+    ))
+}
+
+/// Join lists of a formatted statement written over several lines into one
+/// line, innermost first, when `preferSingleLine` is set for their kind and
+/// the whole line, with the text before and after the list, fits the line
+/// width. Lists with comments, with more items than their threshold or with
+/// items spanning lines are kept. Takes the same arguments as
+/// `wrap_statement`.
+pub fn collapse_lists(
+    line: &str,
+    indent: usize,
+    prefix: usize,
+    config: &Configuration,
+) -> Option<String> {
+    if !line.contains('\n') {
+        return None;
+    }
+    let (keyword, mut code) = match line.strip_prefix("elif ") {
+        Some(code) => ("elif ", code.to_string()),
+        None => ("", line.to_string()),
+    };
+    let first_column = indent + prefix + keyword.len();
+    let mut changed = false;
+    loop {
+        let file = ast::parse_file(&code).ok()?;
+        let comments = ast::parse_comments(&code).ok()?;
+        let mut finder = ListFinder { lists: vec![] };
+        ast::walk_statements(&mut finder, &file.statements);
+        // the finder sees outer lists first
+        let joined = finder.lists.iter().rev().find_map(|list| {
+            let text = list.span.text(&code);
+            let style = config.list_style(list.kind);
+            let keep = !text.contains('\n')
+                || !style.prefer_single_line
+                || style
+                    .always_multiline
+                    .is_some_and(|n| list.items.len() > n as usize)
+                || list
+                    .items
+                    .iter()
+                    .any(|item| item.text(&code).contains('\n'))
+                || comments.iter().any(|c| list.span.contains(c.start.offset));
+            if keep {
+                return None;
+            }
+            let inner = text[1..text.len() - 1]
+                .split('\n')
+                .map(str::trim)
+                .filter(|l| !l.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ");
+            let space = if style.space_inner_bracket { " " } else { "" };
+            let (start, end) = (list.span.start.offset, list.span.end.offset);
+            let joined = format!(
+                "{}{}{}{}{}{}{}",
+                &code[..start],
+                &text[..1],
+                space,
+                inner.strip_suffix(',').unwrap_or(&inner),
+                space,
+                &text[text.len() - 1..],
+                &code[end..]
+            );
+            let line_start = joined[..start].rfind('\n').map_or(0, |i| i + 1);
+            let line_end = joined[start..]
+                .find('\n')
+                .map_or(joined.len(), |i| start + i);
+            let column = if line_start == 0 { first_column } else { 0 };
+            let width = column + joined[line_start..line_end].chars().count();
+            (width <= config.line_width as usize).then_some(joined)
+        });
+        match joined {
+            Some(joined) => {
+                code = joined;
+                changed = true;
+            }
+            None => break,
+        }
+    }
+    changed.then(|| format!("{}{}", keyword, code))
+}
+
+/// a list in brackets and the spans of its items
+struct List {
+    kind: ListKind,
+    span: Span,
+    items: Vec<Span>,
+}
+
+struct ListFinder {
+    lists: Vec<List>,
+}
+
+impl Visitor for ListFinder {
+    fn visit_expr(&mut self, expr: &Expr) -> bool {
+        let (kind, span, items) = match &expr.kind {
+            ExprKind::Array(items) => (
+                ListKind::Array,
+                expr.span,
+                items.iter().map(|e| e.span).collect(),
+            ),
+            ExprKind::Dictionary(items) => (
+                ListKind::Dict,
+                expr.span,
+                items.iter().map(|(k, v)| k.span.to(v.span)).collect(),
+            ),
+            ExprKind::Call { arguments, .. } | ExprKind::MethodCall { arguments, .. } => (
+                ListKind::Arguments,
+                arguments.span,
+                arguments
+                    .positional
+                    .iter()
+                    .map(|e| e.span)
+                    .chain(
+                        arguments
+                            .keywords
+                            .iter()
+                            .map(|k| k.name.span.to(k.value.span)),
+                    )
+                    .collect(),
+            ),
+            _ => return true,
+        };
+        self.lists.push(List { kind, span, items });
+        true
+    }
+}
+
+struct Wrapper<'a> {
+    code: &'a str,
+    config: &'a Configuration,
+    /// indentation of the statement
+    indent: usize,
+    /// column of the first char of `code`
+    first_column: usize,
+}
+
+impl<'a> Wrapper<'a> {
+    /// column where `expr` begins in the statement
+    fn column(&self, expr: &Expr) -> usize {
+        let offset = expr.span.start.offset;
+        let line_start = self.code[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let column = self.code[line_start..offset].chars().count();
+        if line_start == 0 {
+            column + self.first_column
+        } else {
+            column
+        }
+    }
+
+    /// `text` of the statement for a line indented by `indent`
+    fn shift(&self, text: &str, indent: usize) -> String {
+        let shift = " ".repeat(indent - self.indent);
+        text.replace('\n', &format!("\n{}", shift))
+    }
+
+    /// Break `expr` beginning at `column` of a line indented by `indent` at
+    /// its operators of the lowest precedence or before each call of a method
+    /// chain, `in_parens` tells whether line breaks are legal without adding
+    /// parentheses.
+    fn wrap(&self, expr: &Expr, indent: usize, column: usize, in_parens: bool) -> Option<String> {
+        let text = expr.span.text(self.code);
+        // the content of multiline strings can not be indented
+        if text.contains("'''") && text.contains('\n') {
+            return None;
+        }
+        let chain = flatten_chain(expr).map(|(_, calls)| calls.len());
+        let list = match &expr.kind {
+            ExprKind::Call { .. } | ExprKind::Array(_) | ExprKind::Dictionary(_) => true,
+            ExprKind::MethodCall { .. } => {
+                chain < Some(self.config.method_chain_min_length as usize)
+            }
+            _ => false,
+        };
+        // the formatter lays out lists inside other brackets its own way
+        if list {
+            if in_parens {
+                return None;
+            }
+            let filled = self.fill_array(expr, indent);
+            return filled.or_else(|| self.wrap_list(expr, indent, column));
+        }
+        let (inner, parens) = match &expr.kind {
+            ExprKind::Parenthesized(inner) => (inner.as_ref(), true),
+            _ => (expr, false),
+        };
+        if parens
+            && !(self.is_blank(expr.span.start.offset + 1, inner.span.start.offset)
+                && self.is_blank(inner.span.end.offset, expr.span.end.offset - 1))
+        {
+            return None;
+        }
+
+        // only break when a line with one of the breaking points is too long
+        let lines = text.split('\n').collect::<Vec<_>>();
+        let too_long = |breaks: &[usize]| {
+            breaks.iter().any(|line| {
+                let width = match line - expr.span.start.line {
+                    0 => column + lines[0].chars().count(),
+                    n => indent - self.indent + lines[n].chars().count(),
+                };
+                width > self.config.line_width as usize
+            })
+        };
+
+        let open = parens || !in_parens;
+        let inner_indent = indent + self.config.indent_width as usize;
+        let mut result = String::new();
+        let (line_indent, line_column) = if open {
+            result.push_str("(\n");
+            result.push_str(&" ".repeat(inner_indent));
+            (inner_indent, inner_indent)
+        } else {
+            (indent, column)
+        };
+        if let Some((operands, operators)) = flatten(inner) {
+            let mut breaks = vec![];
+            for (pair, operator) in operands.windows(2).zip(&operators) {
+                // comments between operands would be lost
+                let gap = &self.code[pair[0].span.end.offset..pair[1].span.start.offset];
+                if gap.split_whitespace().collect::<Vec<_>>().join(" ") != operator.as_str() {
+                    return None;
+                }
+                breaks.push(pair[0].span.end.line);
+            }
+            if !too_long(&breaks) {
+                return None;
+            }
+            let first = (line_indent, line_column);
+            self.push_operands(&mut result, &operands, &operators, first, inner_indent);
+        } else if let Some((base, calls)) = flatten_chain(inner) {
+            if calls.len() < self.config.method_chain_min_length as usize {
+                return None;
+            }
+            let mut breaks = vec![];
+            let mut starts = vec![];
+            for call in &calls {
+                let ExprKind::MethodCall { object, .. } = &call.kind else {
+                    return None;
+                };
+                // comments before `.` would be lost
+                let gap = &self.code[object.span.end.offset..call.span.end.offset];
+                let start = call.span.end.offset - gap.trim_start().len();
+                if !gap.trim_start().starts_with('.') {
+                    return None;
+                }
+                breaks.push(1 + self.code[..start].matches('\n').count());
+                starts.push(start);
+            }
+            // the formatter joins the lines of a broken chain in parentheses
+            // but keeps the lines of its calls, which are broken again here
+            let joined = parens
+                && std::iter::once(base.span.text(self.code))
+                    .chain(
+                        calls
+                            .iter()
+                            .zip(&starts)
+                            .map(|(call, &start)| &self.code[start..call.span.end.offset]),
+                    )
+                    .any(|segment| segment.contains('\n'));
+            if !too_long(&breaks) && !joined {
+                return None;
+            }
+            let base = match self.wrap(base, line_indent, line_column, true) {
+                Some(wrapped) => wrapped,
+                None => {
+                    let start = base.span.start.offset;
+                    self.wrap_segment(base, start, line_indent, line_column)
+                }
+            };
+            result.push_str(&base);
+            for (call, start) in calls.into_iter().zip(starts) {
+                result.push('\n');
+                result.push_str(&" ".repeat(inner_indent));
+                result.push_str(&self.wrap_segment(call, start, inner_indent, inner_indent));
+            }
+        } else {
+            return None;
+        }
+        if open {
+            result.push('\n');
+            result.push_str(&" ".repeat(indent));
+            result.push(')');
+        }
+        Some(result)
+    }
+
+    /// Break the items of the call, array or dictionary of a statement
+    /// beginning at `column` of a line indented by `indent` one per line, like
+    /// the formatter writes multiline lists, and break the items which are
+    /// still too long. Line breaks are legal inside the brackets, so no
+    /// parentheses are added. A list is only broken for the sake of a binary
+    /// expression in it.
+    fn wrap_list(&self, expr: &Expr, indent: usize, column: usize) -> Option<String> {
+        let (result, wrapped) = self.break_list(expr, expr.span.start.offset, indent, column)?;
+        // a list on one line is broken for its binary expressions
+        let text = expr.span.text(self.code);
+        let width = self.config.line_width as usize;
+        let binary = !text.contains('\n')
+            && column_of_end(&self.code[..expr.span.end.offset], self.first_column) > width
+            && list_items(expr, self.code)?
+                .2
+                .iter()
+                .any(|(_, v)| flatten(v).is_some());
+        (wrapped || binary).then_some(result)
+    }
+
+    /// The array of a statement on one line which is too long, with its
+    /// elements packed into lines like the formatter writes multiline arrays
+    /// with `arrayLayout: fill`.
+    fn fill_array(&self, expr: &Expr, indent: usize) -> Option<String> {
+        let ExprKind::Array(items) = &expr.kind else {
+            return None;
+        };
+        let style = self.config.list_style(ListKind::Array);
+        let width = self.config.line_width as usize;
+        let end = column_of_end(&self.code[..expr.span.end.offset], self.first_column);
+        if !style.fill
+            || items.is_empty()
+            || expr.span.text(self.code).contains('\n')
+            || end <= width
+        {
+            return None;
+        }
+        let mut values = items
+            .iter()
+            .map(|v| {
+                let v = v.span.text(self.code);
+                crate::literal::is_simple_literal(v).then(|| format!("{},", v))
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let inner_indent = indent + self.config.indent_width as usize;
+        let foot = if style.wrap_close_brace {
+            format!("\n{}", " ".repeat(indent))
+        } else {
+            values.last_mut()?.pop();
+            if style.space_inner_bracket { " " } else { "" }.into()
+        };
+        let body = crate::parser::fill_lines(values, width.saturating_sub(inner_indent))
+            .join(&format!("\n{}", " ".repeat(inner_indent)));
+        Some(format!("[\n{}{}{}]", " ".repeat(inner_indent), body, foot))
+    }
+
+    /// `expr` from `start` as a call of a broken method chain, or its base,
+    /// beginning at `column` of a line indented by `indent`. Its arguments are
+    /// broken one per line when it does not fit.
+    fn wrap_segment(&self, expr: &Expr, start: usize, indent: usize, column: usize) -> String {
+        let text = &self.code[start..expr.span.end.offset];
+        let width = self.config.line_width as usize;
+        let broken = (text.contains('\n') || column + text.chars().count() > width)
+            .then(|| self.break_list(expr, start, indent, column))
+            .flatten();
+        broken.map_or_else(|| self.shift(text, indent), |(list, _)| list)
+    }
+
+    /// The list of `expr` from `start` with its items one per line, and
+    /// whether an item too long for its line was broken.
+    fn break_list(
+        &self,
+        expr: &Expr,
+        start: usize,
+        indent: usize,
+        column: usize,
+    ) -> Option<(String, bool)> {
+        let (kind, span, items) = list_items(expr, self.code)?;
+        // comments inside the list would be lost
+        let comments = ast::parse_comments(self.code).ok()?;
+        let range = start..expr.span.end.offset;
+        if comments.iter().any(|c| range.contains(&c.start.offset)) {
+            return None;
+        }
+        let callee = &self.code[start..span.start.offset];
+        if callee.contains('\n')
+            || items
+                .iter()
+                .any(|(_, v)| v.span.text(self.code).contains('\n'))
+        {
+            return None;
+        }
+        let style = self.config.list_style(kind);
+        let width = self.config.line_width as usize;
+        let inner_bracket = if style.space_inner_bracket { " " } else { "" };
+        let inner_indent = indent + self.config.indent_width as usize;
+        let key_width = items
+            .iter()
+            .filter_map(|(k, _)| k.map(str::len))
+            .max()
+            .map_or(0, |n| n + usize::from(style.space_before_colon));
+        let text = &self.code[start..expr.span.end.offset];
+
+        let mut result = format!("{}{}", callee, &text[callee.len()..callee.len() + 1]);
+        let mut column = column + callee.chars().count() + 1;
+        // the first positional argument stays after the name of the function
+        let inline = kind == ListKind::Arguments
+            && items[0].0.is_none()
+            && self.config.nowrap_before_name
+            && crate::parser::takes_name(callee);
+        let mut wrapped = false;
+        for (i, (key, value)) in items.iter().enumerate() {
+            let line_indent = if i == 0 && inline {
+                result.push_str(inner_bracket);
+                column += inner_bracket.len();
+                indent
+            } else {
+                result.push('\n');
+                result.push_str(&" ".repeat(inner_indent));
+                column = inner_indent;
+                inner_indent
+            };
+            if let Some(key) = key {
+                let key = match style.align_colon {
+                    true => format!("{:w$}: ", key, w = key_width),
+                    false => format!("{}: ", key),
+                };
+                column += key.chars().count();
+                result.push_str(&key);
+            }
+            let comma = if i + 1 < items.len() || style.wrap_close_brace {
+                ","
+            } else {
+                ""
+            };
+            let value_text = value.span.text(self.code);
+            let too_long = column + value_text.chars().count() + comma.len() > width;
+            match too_long.then(|| self.wrap(value, line_indent, column, true)) {
+                Some(Some(value)) => {
+                    result.push_str(&value);
+                    wrapped = true;
+                }
+                _ => result.push_str(value_text),
+            }
+            result.push_str(comma);
+        }
+        if style.wrap_close_brace {
+            result.push('\n');
+            result.push_str(&" ".repeat(indent));
+        } else {
+            result.push_str(inner_bracket);
+        }
+        result.push_str(&text[text.len() - 1..]);
+        Some((result, wrapped))
+    }
+
+    /// push operands of binary operators, one per line, the first one at
+    /// `first` indentation and column, others indented by `indent`
+    fn push_operands(
+        &self,
+        result: &mut String,
+        operands: &[&Expr],
+        operators: &[BinaryOperator],
+        first: (usize, usize),
+        indent: usize,
+    ) {
+        let inner_indent_str = " ".repeat(indent);
+        let (mut line_indent, mut line_column) = first;
+        for (i, operand) in operands.iter().enumerate() {
+            if i > 0 {
+                line_indent = indent;
+                let operator = operators[i - 1].as_str();
+                match self.config.operator_position {
+                    OperatorPosition::NextLine => {
+                        result.push('\n');
+                        result.push_str(&inner_indent_str);
+                        result.push_str(operator);
+                        result.push(' ');
+                        line_column = indent + operator.len() + 1;
+                    }
+                    OperatorPosition::SameLine => {
+                        result.push(' ');
+                        result.push_str(operator);
+                        result.push('\n');
+                        result.push_str(&inner_indent_str);
+                        line_column = indent;
+                    }
+                }
+            }
+            match self.wrap(operand, line_indent, line_column, true) {
+                Some(wrapped) => result.push_str(&wrapped),
+                None => result.push_str(&self.shift(operand.span.text(self.code), line_indent)),
+            }
+        }
+    }
+
+    fn is_blank(&self, start: usize, end: usize) -> bool {
+        self.code[start..end].trim().is_empty()
+    }
+}
+
+/// operands and operators of a chain of binary operators with the same
+/// precedence, like `a + b - c`
+fn flatten(expr: &Expr) -> Option<(Vec<&Expr>, Vec<BinaryOperator>)> {
+    let level = match &expr.kind {
+        ExprKind::Binary { operator, .. } => operator.precedence(),
+        _ => return None,
+    };
+    let mut operands = vec![];
+    let mut operators = vec![];
+    let mut current = expr;
+    while let ExprKind::Binary {
+        operator,
+        left,
+        right,
+    } = &current.kind
+    {
+        if operator.precedence() != level {
+            break;
+        }
+        operands.push(right.as_ref());
+        operators.push(*operator);
+        current = left;
+    }
+    operands.push(current);
+    operands.reverse();
+    operators.reverse();
+    Some((operands, operators))
+}
+
+/// the kind of list of a call, array or dictionary, the span of its brackets
+/// and its items with their keys
+#[allow(clippy::type_complexity)]
+fn list_items<'e>(
+    expr: &'e Expr,
+    code: &'e str,
+) -> Option<(ListKind, Span, Vec<(Option<&'e str>, &'e Expr)>)> {
+    let (kind, span, items) = match &expr.kind {
+        ExprKind::Array(items) => (
+            ListKind::Array,
+            expr.span,
+            items.iter().map(|v| (None, v)).collect::<Vec<_>>(),
+        ),
+        ExprKind::Dictionary(items) => (
+            ListKind::Dict,
+            expr.span,
+            items
+                .iter()
+                .map(|(k, v)| (Some(k.span.text(code)), v))
+                .collect(),
+        ),
+        ExprKind::Call { arguments, .. } | ExprKind::MethodCall { arguments, .. } => (
+            ListKind::Arguments,
+            arguments.span,
+            arguments
+                .positional
+                .iter()
+                .map(|v| (None, v))
+                .chain(
+                    arguments
+                        .keywords
+                        .iter()
+                        .map(|k| (Some(k.name.name.as_str()), &k.value)),
+                )
+                .collect(),
+        ),
+        _ => return None,
+    };
+    (!items.is_empty()).then_some((kind, span, items))
+}
+
+/// the column after the last char of `code`, a statement beginning at
+/// `first_column`
+fn column_of_end(code: &str, first_column: usize) -> usize {
+    match code.rfind('\n') {
+        Some(i) => code[i + 1..].chars().count(),
+        None => first_column + code.chars().count(),
+    }
+}
+
+/// the object and the calls on it, innermost first, of a method chain, like
+/// `dep.get_variable('x').strip()`
+fn flatten_chain(expr: &Expr) -> Option<(&Expr, Vec<&Expr>)> {
+    let mut calls = vec![];
+    let mut current = expr;
+    while let ExprKind::MethodCall { object, .. } = &current.kind {
+        calls.push(current);
+        current = object;
+    }
+    if calls.is_empty() {
+        return None;
+    }
+    calls.reverse();
+    Some((current, calls))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::DEFAULT_CONFIGURATION;
+
+    #[test]
+    fn wrap_binary_expressions() {
+        let config = Configuration {
+            line_width: 30,
+            ..DEFAULT_CONFIGURATION
+        };
+        assert_eq!(wrap_statement("x = a + b", 0, 0, &config), None);
+        assert_eq!(
+            wrap_statement("src += files('a.c') + files('b.c')", 0, 0, &config).unwrap(),
+            "src += (\n  files('a.c')\n  + files('b.c')\n)"
+        );
+        assert_eq!(
+            wrap_statement("long_condition or other_one and third", 4, 3, &config).unwrap(),
+            "(\n      long_condition\n      or other_one and third\n    )"
+        );
+        assert_eq!(
+            wrap_statement("elif (first_condition and second_condition)", 0, 0, &config).unwrap(),
+            "elif (\n  first_condition\n  and second_condition\n)"
+        );
+        let config = Configuration {
+            operator_position: OperatorPosition::SameLine,
+            ..config
+        };
+        assert_eq!(
+            wrap_statement(
+                "x = aaaaaaaa * bbbbbbbbbbbbbbb + ccccccccccc",
+                0,
+                0,
+                &config
+            )
+            .unwrap(),
+            "x = (\n  aaaaaaaa * bbbbbbbbbbbbbbb +\n  ccccccccccc\n)"
+        );
+    }
+
+    #[test]
+    fn wrap_nested_operands() {
+        let config = Configuration {
+            line_width: 20,
+            ..DEFAULT_CONFIGURATION
+        };
+        assert_eq!(
+            wrap_statement("x = aaaaaaaaaaaaa * bbbbbbbbbbbb + c", 0, 0, &config).unwrap(),
+            "x = (\n  aaaaaaaaaaaaa\n    * bbbbbbbbbbbb\n  + c\n)"
+        );
+        assert_eq!(
+            wrap_statement("x = [\n  1,\n] + [2222222222222222222]", 0, 0, &config).unwrap(),
+            "x = (\n  [\n    1,\n  ]\n  + [2222222222222222222]\n)"
+        );
+        assert_eq!(
+            wrap_statement("x = '''a\nb''' + 'cccccccccccccccccccc'", 0, 0, &config),
+            None
+        );
+    }
+
+    #[test]
+    fn wrap_list_items() {
+        let config = Configuration {
+            line_width: 30,
+            ..DEFAULT_CONFIGURATION
+        };
+        assert_eq!(
+            wrap_statement("x = f(aaaaaaaaaaaaa + bbbbbbbbbbbbbb)", 0, 0, &config).unwrap(),
+            "x = f(\n  aaaaaaaaaaaaa\n    + bbbbbbbbbbbbbb,\n)"
+        );
+        assert_eq!(
+            wrap_statement("x = ['aaaaaaaaaaaaa' + 'bbbbbbbbbbbb', 'c']", 0, 0, &config).unwrap(),
+            "x = [\n  'aaaaaaaaaaaaa'\n    + 'bbbbbbbbbbbb',\n  'c',\n]"
+        );
+        assert_eq!(
+            wrap_statement("x = ['aaaaaaaaaaaaaaaa', 'bbbbbbbbbbbbbbb']", 0, 0, &config),
+            None
+        );
+    }
+
+    #[test]
+    fn wrap_method_chains() {
+        let config = Configuration {
+            line_width: 40,
+            ..DEFAULT_CONFIGURATION
+        };
+        let text = "dep = subproject('x').get_variable('dep').as_system()";
+        assert_eq!(
+            wrap_statement(text, 0, 0, &config).unwrap(),
+            "dep = (\n  subproject('x')\n  .get_variable('dep')\n  .as_system()\n)"
+        );
+        assert_eq!(
+            wrap_statement(
+                "x = aaaaaaaaaaaaaaaaaaaaaaaaaaa.get_variable('dep')",
+                0,
+                0,
+                &config
+            ),
+            None
+        );
+        assert_eq!(
+            wrap_statement(
+                "x = aaaaaaaaaaaaaa + bbbbbbbbbbbb.cccccccccc().dddd()",
+                0,
+                0,
+                &config
+            )
+            .unwrap(),
+            "x = (\n  aaaaaaaaaaaaaa\n  + bbbbbbbbbbbb.cccccccccc().dddd()\n)"
+        );
+        let broken = "x = (\n  f(\n    aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa,\n    b: 1,\n  )\n  .c(d + e)\n  .g()\n)";
+        let long = "x = f(aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa, b: 1).c(d + e).g()";
+        assert_eq!(wrap_statement(long, 0, 0, &config).unwrap(), broken);
+        // as joined again by the formatter
+        let joined = "x = (f(\n  aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa,\n  b: 1,\n).c(d + e).g())";
+        assert_eq!(wrap_statement(joined, 0, 0, &config).unwrap(), broken);
+        let config = Configuration {
+            method_chain_min_length: 3,
+            ..config
+        };
+        assert_eq!(wrap_statement(text, 0, 0, &config), None);
+    }
+}
@@ -1,8 +1,41 @@
+mod align;
+pub mod ast;
+mod checker;
 mod configuration;
+mod fix;
 mod format_text;
 mod grammar;
+mod graph;
+mod inventory;
+mod layout;
+mod literal;
+mod navigation;
+mod parens;
 mod parser;
+mod project;
+mod refactor;
+mod reflow;
+mod rename;
+mod signatures;
+pub mod version;
 mod wasm_plugin;
 
+pub use checker::{
+    check_file, check_path, check_text, project_meson_version, Diagnostic, Severity,
+};
 pub use configuration::Configuration;
+pub use fix::{fix_file_name, fix_text, FixOptions};
+pub use format_text::format_text;
+pub use graph::{build_graph, project_graph, BuildGraph, Reference, Target};
+pub use inventory::{inventory, project_inventory, Inventory, InventoryEntry};
+pub use navigation::{find_definitions, find_references, Location};
+pub use parser::ParseError;
+pub use project::{
+    check_project, discover_project, format_project, FileReport, MissingFile, Outcome, Project,
+    ProjectFile,
+};
+pub use refactor::{
+    apply_edits, convert_string_at, convert_strings, ConversionError, StringStyle, TextEdit,
+};
+pub use rename::{rename, rename_request, rename_text, RenameError, RenamedFile};
 pub use wasm_plugin::*;
@@ -0,0 +1,320 @@
+use crate::configuration::Configuration;
+use std::fmt::Write;
+
+/// a part of a single line string
+#[derive(Debug, PartialEq, Eq)]
+enum Piece<'a> {
+    /// a plain or escaped character
+    Char(char),
+    /// an escape which meson keeps as written, like `\N{...}` or `\d`
+    Raw(&'a str),
+}
+
+/// Rewrite a string literal (with quotes, without the `f` prefix) into its
+/// canonical form.
+///
+/// Single line strings get canonical escapes and become `'''` strings when
+/// they contain a newline, multiline strings keep their content since meson
+/// does not process escapes in them.
+pub fn normalize_string(literal: &str, format: bool, config: &Configuration) -> String {
+    let multiline = literal.len() >= 6 && literal.starts_with("'''");
+    if multiline {
+        let content = &literal[3..literal.len() - 3];
+        if config.collapse_multiline_strings && !content.contains('\n') {
+            let mut result = String::from("'");
+            content.chars().for_each(|c| escape(c, &mut result));
+            result.push('\'');
+            return result;
+        }
+        return literal.to_string();
+    }
+    if !config.normalize_strings {
+        return literal.to_string();
+    }
+
+    let pieces = decode(&literal[1..literal.len() - 1]);
+    if !format && can_be_multiline(&pieces) {
+        let mut result = String::from("'''");
+        for piece in &pieces {
+            if let Piece::Char(c) = piece {
+                result.push(*c);
+            }
+        }
+        result.push_str("'''");
+        return result;
+    }
+    let mut result = String::from("'");
+    for piece in &pieces {
+        match piece {
+            Piece::Char(c) => escape(*c, &mut result),
+            Piece::Raw(s) => result.push_str(s),
+        }
+    }
+    result.push('\'');
+    result
+}
+
+/// the text is an integer literal of meson, like `42`, `0x1F`, `0o755` or
+/// `0b101`
+pub fn is_integer(literal: &str) -> bool {
+    let (digits, radix) = split_integer(literal);
+    !digits.is_empty() && digits.chars().all(|c| c.is_digit(radix))
+}
+
+/// the formatted text is one boolean, integer or single line string
+pub fn is_simple_literal(text: &str) -> bool {
+    if text == "true" || text == "false" {
+        return true;
+    }
+    if let Some(content) = text.strip_prefix('f').unwrap_or(text).strip_prefix('\'') {
+        let mut escaped = false;
+        for (i, c) in content.char_indices() {
+            match c {
+                '\'' if !escaped => return i + 1 == content.len(),
+                '\n' => return false,
+                _ => escaped = c == '\\' && !escaped,
+            }
+        }
+        return false;
+    }
+    is_integer(text.strip_prefix('-').unwrap_or(text))
+}
+
+/// Rewrite an integer literal in lowercase, like the hex escapes of strings,
+/// and without leading zeros.
+pub fn normalize_integer(literal: &str) -> String {
+    let (digits, _) = split_integer(literal);
+    let prefix = &literal[..literal.len() - digits.len()];
+    let digits = match digits.trim_start_matches('0') {
+        "" => "0",
+        digits => digits,
+    };
+    format!("{}{}", prefix, digits).to_ascii_lowercase()
+}
+
+/// digits and radix of an integer literal
+fn split_integer(literal: &str) -> (&str, u32) {
+    let radix = match literal.get(..2).map(|p| p.to_ascii_lowercase()).as_deref() {
+        Some("0x") => 16,
+        Some("0o") => 8,
+        Some("0b") => 2,
+        _ => return (literal, 10),
+    };
+    (&literal[2..], radix)
+}
+
+/// the string has a newline and reads the same without escapes
+fn can_be_multiline(pieces: &[Piece]) -> bool {
+    let chars = pieces
+        .iter()
+        .map(|p| match p {
+            Piece::Char(c) => Some(*c),
+            Piece::Raw(_) => None,
+        })
+        .collect::<Option<String>>();
+    match chars {
+        Some(s) => {
+            s.contains('\n')
+                && !s.contains("'''")
+                && !s.ends_with('\'')
+                && !s.contains('\\')
+                && !s.chars().any(|c| c.is_control() && c != '\n' && c != '\t')
+        }
+        None => false,
+    }
+}
+
+fn escape(c: char, result: &mut String) {
+    match c {
+        '\\' => result.push_str("\\\\"),
+        '\'' => result.push_str("\\'"),
+        '\n' => result.push_str("\\n"),
+        '\t' => result.push_str("\\t"),
+        '\r' => result.push_str("\\r"),
+        '\x07' => result.push_str("\\a"),
+        '\x08' => result.push_str("\\b"),
+        '\x0b' => result.push_str("\\v"),
+        '\x0c' => result.push_str("\\f"),
+        c if c.is_control() => {
+            let _ = match c as u32 {
+                n @ 0..=0xff => write!(result, "\\x{:02x}", n),
+                n @ 0x100..=0xffff => write!(result, "\\u{:04x}", n),
+                n => write!(result, "\\U{:08x}", n),
+            };
+        }
+        c => result.push(c),
+    }
+}
+
+/// split the content of a single line string into characters
+fn decode(content: &str) -> Vec<Piece<'_>> {
+    let mut pieces = vec![];
+    let mut rest = content;
+    while let Some(c) = rest.chars().next() {
+        if c != '\\' {
+            pieces.push(Piece::Char(c));
+            rest = &rest[c.len_utf8()..];
+            continue;
+        }
+        let (piece, len) = decode_escape(rest);
+        pieces.push(piece);
+        rest = &rest[len..];
+    }
+    pieces
+}
+
+/// decode the escape at the beginning of `text`, returns the piece and its
+/// length in bytes
+fn decode_escape(text: &str) -> (Piece<'_>, usize) {
+    let next = match text[1..].chars().next() {
+        Some(c) => c,
+        None => return (Piece::Raw(text), text.len()),
+    };
+    let simple = match next {
+        '\\' => Some('\\'),
+        '\'' => Some('\''),
+        'a' => Some('\x07'),
+        'b' => Some('\x08'),
+        'f' => Some('\x0c'),
+        'n' => Some('\n'),
+        'r' => Some('\r'),
+        't' => Some('\t'),
+        'v' => Some('\x0b'),
+        _ => None,
+    };
+    if let Some(c) = simple {
+        return (Piece::Char(c), 2);
+    }
+
+    let code = |digits: usize, radix: u32| {
+        let end = text
+            .char_indices()
+            .skip(2)
+            .take(digits)
+            .take_while(|(_, c)| c.is_digit(radix))
+            .map(|(i, c)| i + c.len_utf8())
+            .last()?;
+        Some((u32::from_str_radix(&text[2..end], radix).ok()?, end))
+    };
+    let decoded = match next {
+        'x' => code(2, 16).filter(|(_, end)| *end == 4),
+        'u' => code(4, 16).filter(|(_, end)| *end == 6),
+        'U' => code(8, 16).filter(|(_, end)| *end == 10),
+        '0'..='7' => {
+            // the first digit is part of the code too
+            let end = text
+                .char_indices()
+                .skip(1)
+                .take(3)
+                .take_while(|(_, c)| c.is_digit(8))
+                .map(|(i, _)| i + 1)
+                .last()
+                .unwrap_or(2);
+            u32::from_str_radix(&text[1..end], 8).ok().map(|n| (n, end))
+        }
+        _ => None,
+    };
+    if let Some((c, end)) = decoded.and_then(|(n, end)| Some((char::from_u32(n)?, end))) {
+        return (Piece::Char(c), end);
+    }
+
+    let len = match next {
+        'N' if text[2..].starts_with('{') => text.find('}').map_or(2, |i| i + 1),
+        c => 1 + c.len_utf8(),
+    };
+    (Piece::Raw(&text[..len]), len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::DEFAULT_CONFIGURATION;
+
+    #[test]
+    fn normalize_escapes() {
+        let config = Configuration {
+            normalize_strings: true,
+            ..DEFAULT_CONFIGURATION
+        };
+        for (src, dst) in [
+            ("''", "''"),
+            ("'a\\x41'", "'aA'"),
+            ("'\\u00e9\\U0001F600'", "'é😀'"),
+            ("'\\101\\0'", "'A\\x00'"),
+            ("'\\x1B[0m'", "'\\x1b[0m'"),
+            ("'\\\\\\''", "'\\\\\\''"),
+            ("'\\x5c\\x27'", "'\\\\\\''"),
+            ("'\\\"\\d\\N{DASH}'", "'\\\"\\d\\N{DASH}'"),
+            ("'\\x4g\\u12'", "'\\x4g\\u12'"),
+            ("'\\t\\x09'", "'\\t\\t'"),
+            ("'a\\nb'", "'''a\nb'''"),
+            ("'a\\n\\\\b'", "'a\\n\\\\b'"),
+            ("'a\\n\\''", "'a\\n\\''"),
+            ("'a\\n\\r'", "'a\\n\\r'"),
+            ("'\\n\\d'", "'\\n\\d'"),
+            ("'''a\\x41'''", "'''a\\x41'''"),
+        ] {
+            assert_eq!(normalize_string(src, false, &config), dst, "{}", src);
+        }
+        assert_eq!(normalize_string("'@a@\\n'", true, &config), "'@a@\\n'");
+    }
+
+    #[test]
+    fn integers() {
+        for (src, dst) in [
+            ("0", Some("0")),
+            ("42", Some("42")),
+            ("007", Some("7")),
+            ("0XFF", Some("0xff")),
+            ("0x00AB", Some("0xab")),
+            ("0o0755", Some("0o755")),
+            ("0B1010", Some("0b1010")),
+            ("0b000", Some("0b0")),
+            ("0x", None),
+            ("0b102", None),
+            ("0o8", None),
+            ("12ab", None),
+            ("1_000", None),
+        ] {
+            assert_eq!(is_integer(src), dst.is_some(), "{}", src);
+            if let Some(dst) = dst {
+                assert_eq!(normalize_integer(src), dst);
+            }
+        }
+    }
+
+    #[test]
+    fn simple_literals() {
+        for (src, simple) in [
+            ("true", true),
+            ("-0x1F", true),
+            ("'-Wall'", true),
+            ("f'@a@'", true),
+            ("'a\\'b'", true),
+            ("''", true),
+            ("'a' + 'b'", false),
+            ("'''a'''", false),
+            ("foo", false),
+            ("f(1)", false),
+        ] {
+            assert_eq!(is_simple_literal(src), simple, "{}", src);
+        }
+    }
+
+    #[test]
+    fn collapse_multiline() {
+        let config = Configuration {
+            collapse_multiline_strings: true,
+            ..DEFAULT_CONFIGURATION
+        };
+        for (src, dst) in [
+            ("''''''", "''"),
+            ("'''it's'''", "'it\\'s'"),
+            ("'''a\\nb'''", "'a\\\\nb'"),
+            ("'''a\nb'''", "'''a\nb'''"),
+            ("'a\\x41'", "'a\\x41'"),
+        ] {
+            assert_eq!(normalize_string(src, false, &config), dst, "{}", src);
+        }
+    }
+}
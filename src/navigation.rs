@@ -0,0 +1,372 @@
+use crate::ast::{self, AssignOperator, Expr, Span, Statement};
+use crate::parser::ParseError;
+use crate::project::{discover_project, SubdirFinder};
+use crate::rename::{occurrences, Occurrence};
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// a variable name in a build file of a project
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    /// path relative to the project root
+    pub path: PathBuf,
+    pub span: Span,
+    /// assigned by `=`, `+=` or `foreach`
+    pub definition: bool,
+}
+
+/// Find the assignments which may give the variable at `offset` of the file
+/// its value, following the `subdir()` calls from the top level `meson.build`
+/// of the project at `root` like meson reads it.
+///
+/// `path` is relative to `root` and `file_text` its current text, which may
+/// not be saved yet. A definition finds itself, an assignment hides the ones
+/// before it unless they are in other branches of an `if`. Returns nothing
+/// when there is no variable at `offset`.
+pub fn find_definitions(
+    root: &Path,
+    path: &Path,
+    file_text: &str,
+    offset: usize,
+) -> Result<Vec<Location>, ParseError> {
+    let (name, occurrence) = match variable_at(file_text, offset)? {
+        Some(found) => found,
+        None => return Ok(vec![]),
+    };
+    if occurrence.definition {
+        return Ok(vec![Location {
+            path: path.to_path_buf(),
+            span: occurrence.span,
+            definition: true,
+        }]);
+    }
+    let mut reaching = Reaching {
+        root,
+        name: &name,
+        query: (path, file_text, occurrence.span),
+        file: PathBuf::new(),
+        read: HashSet::new(),
+        found: None,
+    };
+    reaching.run_file(PathBuf::from("meson.build"), vec![]);
+    // files `subdir()` does not reach are read alone
+    if reaching.found.is_none() {
+        reaching.read.clear();
+        reaching.run_file(path.to_path_buf(), vec![]);
+    }
+    Ok(reaching.found.unwrap_or_default())
+}
+
+/// Find every occurrence of the variable at `offset` of the file in the
+/// files of the project at `root` reached by `subdir()`, which share one
+/// scope, and in the file itself. Files which can not be read or parsed are
+/// skipped.
+pub fn find_references(
+    root: &Path,
+    path: &Path,
+    file_text: &str,
+    offset: usize,
+) -> Result<Vec<Location>, ParseError> {
+    let name = match variable_at(file_text, offset)? {
+        Some((name, _)) => name,
+        None => return Ok(vec![]),
+    };
+    let mut paths = discover_project(root)
+        .files
+        .into_iter()
+        .filter(|f| f.reached)
+        .map(|f| f.path)
+        .collect::<Vec<_>>();
+    if !paths.iter().any(|p| p == path) {
+        paths.push(path.to_path_buf());
+    }
+    let mut locations = vec![];
+    for p in paths {
+        let text = if p == path {
+            file_text.to_string()
+        } else {
+            match fs::read_to_string(root.join(&p)) {
+                Ok(text) => text,
+                Err(_) => continue,
+            }
+        };
+        let file = match ast::parse_file(&text) {
+            Ok(file) => file,
+            Err(_) => continue,
+        };
+        locations.extend(
+            occurrences(&text, &file, &name)
+                .into_iter()
+                .map(|o| Location {
+                    path: p.clone(),
+                    span: o.span,
+                    definition: o.definition,
+                }),
+        );
+    }
+    Ok(locations)
+}
+
+/// the variable name around `offset` and its occurrence
+pub(crate) fn variable_at(
+    text: &str,
+    offset: usize,
+) -> Result<Option<(String, Occurrence)>, ParseError> {
+    let file = ast::parse_file(text)?;
+    let offset = offset.min(text.len());
+    // identifiers are ascii, an offset inside another char is not in one
+    if !text.is_char_boundary(offset) {
+        return Ok(None);
+    }
+    let start = text[..offset]
+        .char_indices()
+        .rev()
+        .find(|(_, c)| !crate::grammar::is_identifier(*c))
+        .map_or(0, |(i, c)| i + c.len_utf8());
+    let end = text[offset..]
+        .find(|c: char| !crate::grammar::is_identifier(c))
+        .map_or(text.len(), |i| offset + i);
+    let name = &text[start..end];
+    if name.is_empty() {
+        return Ok(None);
+    }
+    Ok(occurrences(text, &file, name)
+        .into_iter()
+        .find(|o| o.span.start.offset == start)
+        .map(|o| (name.to_string(), o)))
+}
+
+/// reaching definitions of one variable in the order meson reads the files
+struct Reaching<'a> {
+    root: &'a Path,
+    name: &'a str,
+    /// the file asked about, its text and the span of the variable
+    query: (&'a Path, &'a str, Span),
+    /// the file being read
+    file: PathBuf,
+    read: HashSet<PathBuf>,
+    /// definitions reaching the variable asked about
+    found: Option<Vec<Location>>,
+}
+
+impl Reaching<'_> {
+    /// read a file with the definitions reaching its beginning, returns the
+    /// ones reaching its end
+    fn run_file(&mut self, path: PathBuf, state: Vec<Location>) -> Vec<Location> {
+        if !self.read.insert(path.clone()) {
+            return state;
+        }
+        let text = if path == self.query.0 {
+            self.query.1.to_string()
+        } else {
+            match fs::read_to_string(self.root.join(&path)) {
+                Ok(text) => text,
+                Err(_) => return state,
+            }
+        };
+        let file = match ast::parse_file(&text) {
+            Ok(file) => file,
+            Err(_) => return state,
+        };
+        let parent = std::mem::replace(&mut self.file, path);
+        let state = self.run_block(&file.statements, state);
+        self.file = parent;
+        state
+    }
+
+    fn run_block(&mut self, statements: &[Statement], mut state: Vec<Location>) -> Vec<Location> {
+        for statement in statements {
+            state = self.run_statement(statement, state);
+        }
+        state
+    }
+
+    fn run_statement(&mut self, statement: &Statement, state: Vec<Location>) -> Vec<Location> {
+        match statement {
+            Statement::Expression(expr) => {
+                self.use_in(expr, &state);
+                self.subdirs(expr, state)
+            }
+            Statement::Assignment {
+                target,
+                operator,
+                value,
+                ..
+            } => {
+                self.use_in(value, &state);
+                if target.name != self.name {
+                    return state;
+                }
+                let definition = self.location(target.span);
+                match operator {
+                    AssignOperator::Assign => vec![definition],
+                    AssignOperator::AddAssign => union(state, vec![definition]),
+                }
+            }
+            Statement::If {
+                branches,
+                else_block,
+                ..
+            } => {
+                let mut result = vec![];
+                for (condition, block) in branches {
+                    self.use_in(condition, &state);
+                    let branch = self.run_block(block, state.clone());
+                    result = union(result, branch);
+                }
+                match else_block {
+                    Some(block) => {
+                        let branch = self.run_block(block, state);
+                        union(result, branch)
+                    }
+                    None => union(result, state),
+                }
+            }
+            Statement::Foreach {
+                variables,
+                items,
+                body,
+                ..
+            } => {
+                self.use_in(items, &state);
+                let mut entry = state.clone();
+                for variable in variables.iter().filter(|v| v.name == self.name) {
+                    entry = vec![self.location(variable.span)];
+                }
+                // the second pass sees the definitions of earlier iterations
+                let first = self.run_block(body, entry.clone());
+                let last = self.run_block(body, union(entry, first));
+                union(state, last)
+            }
+            Statement::Break(_) | Statement::Continue(_) => state,
+        }
+    }
+
+    /// record the definitions reaching the variable asked about when it is
+    /// in `expr`
+    fn use_in(&mut self, expr: &Expr, state: &[Location]) {
+        let (path, _, span) = self.query;
+        if self.file == path && expr.span.contains(span.start.offset) {
+            self.found = Some(state.to_vec());
+        }
+    }
+
+    /// read the files of the `subdir()` calls in `expr`
+    fn subdirs(&mut self, expr: &Expr, mut state: Vec<Location>) -> Vec<Location> {
+        let mut finder = SubdirFinder { calls: vec![] };
+        ast::walk_expr(&mut finder, expr);
+        for (dir, _) in finder.calls {
+            let parent = self.file.parent().unwrap_or_else(|| Path::new(""));
+            let path = parent.join(dir).join("meson.build");
+            state = self.run_file(path, state);
+        }
+        state
+    }
+
+    fn location(&self, span: Span) -> Location {
+        Location {
+            path: self.file.clone(),
+            span,
+            definition: true,
+        }
+    }
+}
+
+/// definitions of `a` and `b`
+fn union(mut a: Vec<Location>, b: Vec<Location>) -> Vec<Location> {
+    for location in b {
+        if !a.contains(&location) {
+            a.push(location);
+        }
+    }
+    a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(locations: &[Location]) -> Vec<(&str, usize)> {
+        locations
+            .iter()
+            .map(|l| (l.path.to_str().unwrap(), l.span.start.line))
+            .collect()
+    }
+
+    #[test]
+    fn definitions_in_file() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/none");
+        let text = "x = 1
+if a
+  x = 2
+elif b
+  x += 3
+endif
+y = x
+x = 4
+foreach i : [1]
+  message(x)
+  x = i
+endforeach
+message(f'@x@')
+";
+        let path = Path::new("meson.build");
+        let at = |line: usize, column: usize| {
+            let offset = text
+                .split('\n')
+                .take(line - 1)
+                .map(|l| l.len() + 1)
+                .sum::<usize>();
+            let found = find_definitions(&root, path, text, offset + column - 1).unwrap();
+            lines(&found)
+                .into_iter()
+                .map(|(_, line)| line)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(at(7, 5), [3, 1, 5]);
+        assert_eq!(at(10, 11), [8, 11]);
+        assert_eq!(at(13, 12), [8, 11]);
+        // the definition itself
+        assert_eq!(at(8, 1), [8]);
+        assert_eq!(at(7, 4), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn offsets_after_non_ascii() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/none");
+        let path = Path::new("meson.build");
+        let text = "x = 'é'\ny = x # é\n";
+        assert_eq!(find_definitions(&root, path, text, 7).unwrap(), []);
+        // inside `é`
+        assert_eq!(find_definitions(&root, path, text, 6).unwrap(), []);
+        assert_eq!(find_references(&root, path, text, 18).unwrap(), []);
+        let found = find_definitions(&root, path, text, 13).unwrap();
+        assert_eq!(lines(&found), [("meson.build", 1)]);
+    }
+
+    #[test]
+    fn definitions_in_project() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/rename");
+        let path = Path::new("sub/meson.build");
+        let text = fs::read_to_string(root.join(path)).unwrap();
+        let offset = text.find("lib)").unwrap();
+        let found = find_definitions(&root, path, &text, offset).unwrap();
+        assert_eq!(lines(&found), [("meson.build", 3)]);
+
+        let found = find_references(&root, path, &text, offset).unwrap();
+        assert_eq!(
+            found
+                .iter()
+                .map(|l| (l.path.to_str().unwrap(), l.span.start.line, l.definition))
+                .collect::<Vec<_>>(),
+            [
+                ("meson.build", 3, true),
+                ("meson.build", 5, false),
+                ("sub/meson.build", 1, false),
+            ]
+        );
+    }
+}
@@ -0,0 +1,161 @@
+use crate::ast::{self, Expr, ExprKind, Statement, UnaryOperator};
+use crate::refactor::{apply_edits, TextEdit};
+
+/// Remove grouping parentheses of a formatted statement which do not change
+/// its meaning. Returns `None` when there are none.
+pub fn remove_redundant_parens(line: &str) -> Option<String> {
+    if !line.contains('(') {
+        return None;
+    }
+    let (keyword, code) = match line.strip_prefix("elif ") {
+        Some(code) => ("elif ", code),
+        None => ("", line),
+    };
+    let mut text = code.to_string();
+    loop {
+        let file = ast::parse_file(&text).ok()?;
+        let expr = match file.statements.as_slice() {
+            [Statement::Expression(expr)] | [Statement::Assignment { value: expr, .. }] => expr,
+            _ => return None,
+        };
+        let mut edits = vec![];
+        simplify(&text, expr, Context::Free, &mut edits);
+        if edits.is_empty() {
+            break;
+        }
+        // nested parentheses are removed in the next round
+        text = apply_edits(&text, &edits);
+    }
+    (text != code).then(|| format!("{}{}", keyword, text))
+}
+
+/// where an expression appears
+#[derive(Debug, Clone, Copy)]
+enum Context {
+    /// any expression is allowed, like an argument
+    Free,
+    /// condition or branch of a ternary operator
+    Ternary,
+    /// operand of a binary operator, binary expressions with at least this
+    /// precedence do not need parentheses
+    Operand(usize),
+    /// operand of an unary operator or object of a call or index
+    Atom,
+}
+
+fn simplify(code: &str, expr: &Expr, context: Context, edits: &mut Vec<TextEdit>) {
+    let recurse =
+        |e: &Expr, context: Context, edits: &mut Vec<TextEdit>| simplify(code, e, context, edits);
+    match &expr.kind {
+        ExprKind::Parenthesized(inner) => {
+            let redundant = match (&inner.kind, context) {
+                (_, Context::Free) => true,
+                (ExprKind::Ternary { .. }, _) => false,
+                (ExprKind::Binary { operator, .. }, Context::Operand(level)) => {
+                    operator.precedence() >= level
+                }
+                (ExprKind::Binary { .. }, context) => matches!(context, Context::Ternary),
+                // `(not a) == b` is clearer than `not a == b`
+                (
+                    ExprKind::Unary {
+                        operator: UnaryOperator::Not,
+                        ..
+                    },
+                    Context::Operand(_),
+                ) => false,
+                (ExprKind::Unary { .. }, context) => !matches!(context, Context::Atom),
+                _ => true,
+            };
+            // comments inside parentheses would be lost
+            let blank = code[expr.span.start.offset + 1..inner.span.start.offset]
+                .trim()
+                .is_empty()
+                && code[inner.span.end.offset..expr.span.end.offset - 1]
+                    .trim()
+                    .is_empty();
+            if redundant && blank {
+                edits.push(TextEdit {
+                    span: expr.span,
+                    new_text: inner.span.text(code).into(),
+                });
+            } else {
+                recurse(inner, Context::Free, edits);
+            }
+        }
+        ExprKind::Array(items) => items.iter().for_each(|e| recurse(e, Context::Free, edits)),
+        ExprKind::Dictionary(items) => items.iter().for_each(|(k, v)| {
+            recurse(k, Context::Free, edits);
+            recurse(v, Context::Free, edits);
+        }),
+        ExprKind::Call { arguments, .. } | ExprKind::MethodCall { arguments, .. } => {
+            if let ExprKind::MethodCall { object, .. } = &expr.kind {
+                recurse(object, Context::Atom, edits);
+            }
+            for e in &arguments.positional {
+                recurse(e, Context::Free, edits);
+            }
+            for k in &arguments.keywords {
+                recurse(&k.value, Context::Free, edits);
+            }
+        }
+        ExprKind::Index { object, index } => {
+            recurse(object, Context::Atom, edits);
+            recurse(index, Context::Free, edits);
+        }
+        ExprKind::Unary { operand, .. } => recurse(operand, Context::Atom, edits),
+        ExprKind::Binary {
+            operator,
+            left,
+            right,
+        } => {
+            let level = operator.precedence();
+            // meson does not chain comparisons like `a == b == c`
+            let left_level = if level == 2 { level + 1 } else { level };
+            recurse(left, Context::Operand(left_level), edits);
+            recurse(right, Context::Operand(level + 1), edits);
+        }
+        ExprKind::Ternary {
+            condition,
+            if_true,
+            if_false,
+        } => {
+            for e in [condition, if_true, if_false] {
+                recurse(e, Context::Ternary, edits);
+            }
+        }
+        ExprKind::Identifier(_) | ExprKind::Bool(_) | ExprKind::Number(_) | ExprKind::String(_) => {
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remove_parens() {
+        for (src, dst) in [
+            ("x = (a + b)", Some("x = a + b")),
+            ("x = (a + b) * c", None),
+            ("x = a * (b * c)", None),
+            ("x = (a * b) + c", Some("x = a * b + c")),
+            ("x = (a - b) - c", Some("x = a - b - c")),
+            ("x = a - (b - c)", None),
+            ("x = (a == b) == c", None),
+            ("x = ((a)).b((c)) + -(d)", Some("x = a.b(c) + -d")),
+            ("x = (a + b).c()", None),
+            ("x = (not a) == b", None),
+            ("x = a or (b and c)", Some("x = a or b and c")),
+            ("x = (a ? b : c) ? d : e", None),
+            (
+                "x = ((a or b)) ? c : d # c\n",
+                Some("x = a or b ? c : d # c\n"),
+            ),
+            ("elif (a)", Some("elif a")),
+            ("x = (a # c\n  + b)", None),
+            ("foreach a : (b)", None),
+        ] {
+            assert_eq!(remove_redundant_parens(src).as_deref(), dst, "{}", src);
+        }
+    }
+}
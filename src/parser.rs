@@ -1,10 +1,18 @@
-use crate::configuration::Configuration;
+use crate::configuration::{CommentStyle, Configuration, ListKind};
 use std::{error::Error, fmt, iter::Peekable, str::Chars};
 
 use std::fmt::Write as FmtWrite;
 
 type Result<T = ()> = std::result::Result<T, ParseError>;
 
+/// Format chars to stage while reading them, so comments and the line breaks
+/// of the input are at hand.
+///
+/// The syntax tree of `ast` is only built by the passes needing it: `layout`
+/// for statements longer than the line width, and the opt-in `parens`,
+/// `align` and `reflow`. This parser lets through some text meson rejects,
+/// like `x = a b`, so `format_text` also parses the text with `ast` and
+/// returns its error. `parsers_agree` in `format_text` tests the result.
 pub fn parse(config: &Configuration, chars: &mut Chars, stage: &mut String) -> Result {
     Parser::new(config, chars).parse_to_stage(stage)
 }
@@ -13,6 +21,12 @@ pub fn parse(config: &Configuration, chars: &mut Chars, stage: &mut String) -> R
 pub enum ParseError {
     InvalidSyntaxClose(String),
     NotFindSyntaxClose(String),
+    UnexpectedSyntax {
+        found: String,
+        expected: String,
+        line: usize,
+        column: usize,
+    },
     FmtError(fmt::Error),
 }
 impl Error for ParseError {}
@@ -22,27 +36,96 @@ impl fmt::Display for ParseError {
         match self {
             Self::InvalidSyntaxClose(s) => write!(f, "invalid the close of syntax: {}", s)?,
             Self::NotFindSyntaxClose(s) => write!(f, "not find the close of syntax: {}", s)?,
+            Self::UnexpectedSyntax {
+                found,
+                expected,
+                line,
+                column,
+            } => write!(
+                f,
+                "unexpected {} at {}:{}, expected {}",
+                found, line, column, expected
+            )?,
             Self::FmtError(e) => write!(f, "{}", e)?,
         }
         Ok(())
     }
 }
 
+impl ParseError {
+    fn unexpected(
+        found: impl Into<String>,
+        expected: impl Into<String>,
+        (line, column): (usize, usize),
+    ) -> Self {
+        Self::UnexpectedSyntax {
+            found: found.into(),
+            expected: expected.into(),
+            line,
+            column,
+        }
+    }
+}
+
 impl From<fmt::Error> for ParseError {
     fn from(e: fmt::Error) -> Self {
         Self::FmtError(e)
     }
 }
 
+/// chars of the source text with the position of the last one
+struct Source<'a, 'b> {
+    chars: Peekable<&'a mut Chars<'b>>,
+    line: usize,
+    column: usize,
+    /// the last char is `\n`, the next one begins a line
+    after_newline: bool,
+}
+impl<'a, 'b> Source<'a, 'b> {
+    fn peek(&mut self) -> Option<&char> {
+        self.chars.peek()
+    }
+    /// line and column of the last char
+    fn position(&self) -> (usize, usize) {
+        (self.line, self.column)
+    }
+    /// the next char as written, for strings and comments
+    fn next_raw(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if self.after_newline {
+            self.line += 1;
+            self.column = 0;
+        }
+        self.column += 1;
+        self.after_newline = c == '\n';
+        Some(c)
+    }
+}
+impl Iterator for Source<'_, '_> {
+    type Item = char;
+    /// the next char, tabs and form feeds are read as spaces
+    fn next(&mut self) -> Option<char> {
+        match self.next_raw()? {
+            '\t' | '\x0c' => Some(' '),
+            c => Some(c),
+        }
+    }
+}
+
 struct Parser<'a, 'b> {
     config: &'a Configuration,
-    chars: Peekable<&'a mut Chars<'b>>,
+    chars: Source<'a, 'b>,
 }
 impl<'a, 'b> Parser<'a, 'b> {
     fn new(config: &'a Configuration, chars: &'a mut Chars<'b>) -> Self {
         Self {
             config,
-            chars: chars.peekable(),
+            chars: Source {
+                chars: chars.peekable(),
+                line: 1,
+                column: 0,
+                after_newline: false,
+            },
         }
     }
 
@@ -53,16 +136,20 @@ impl<'a, 'b> Parser<'a, 'b> {
         loop {
             match self.chars.next() {
                 None => {
-                    buff.merge_span_to_line();
+                    self.check_line_end(&mut buff, "end of file")?;
+                    self.finish_statement(&mut buff, 0, 0);
                     stage.push_str(&buff.line);
                     return Ok(());
                 }
                 Some('#') => {
-                    buff.merge_span_to_line();
+                    self.check_line_end(&mut buff, "comment")?;
                     self.parse_comment(&mut buff.span)?;
+                    self.finish_statement(&mut buff, 0, 0);
                     buff.move_line_to_stage(stage);
                 }
                 Some('\n') => {
+                    self.check_line_end(&mut buff, "end of line")?;
+                    self.finish_statement(&mut buff, 0, 0);
                     buff.move_line_to_stage(stage);
                     stage.push('\n');
                 }
@@ -78,7 +165,10 @@ impl<'a, 'b> Parser<'a, 'b> {
                     "elif" | "else" | "endif" | "endforeach" => {
                         return Err(ParseError::InvalidSyntaxClose(buff.span))
                     }
-                    _ => buff.merge_span_to_line(),
+                    _ => {
+                        self.check_line_start(&buff)?;
+                        buff.merge_word()
+                    }
                 },
                 Some(c) => self.match_char_parse(&mut buff, c, 0)?,
             }
@@ -88,19 +178,26 @@ impl<'a, 'b> Parser<'a, 'b> {
     fn match_char_parse(&mut self, buff: &mut Buffer, c: char, indent: u8) -> Result {
         match c {
             '\'' => {
-                if buff.span != "f" {
-                    buff.merge_span_to_line();
-                }
-                self.parse_string(&mut buff.span)?;
+                let format = buff.take_format_prefix();
+                buff.merge_word();
+                self.parse_string(&mut buff.span, format)?;
                 buff.merge_span_to_line();
                 buff.last_identifier = true;
             }
-            '(' => {
+            '(' if buff.is_call() => {
                 buff.merge_span_to_line();
                 buff.move_line_to_span();
                 self.parse_argument(&mut buff.span, indent)?;
                 buff.last_identifier = true;
             }
+            '(' => {
+                // keep unary operators close to the parentheses
+                if !(buff.last_identifier && buff.span == "-") {
+                    buff.merge_span_to_line();
+                }
+                self.parse_group(&mut buff.span, indent)?;
+                buff.last_identifier = true;
+            }
             '[' => {
                 buff.merge_span_to_line();
                 // "[]" may be index of array
@@ -116,162 +213,334 @@ impl<'a, 'b> Parser<'a, 'b> {
                 buff.last_identifier = true;
             }
             ')' | ']' | '}' => return Err(ParseError::InvalidSyntaxClose(c.into())),
+            '"' => {
+                let expected = "a string quoted with `'`";
+                return Err(ParseError::unexpected(
+                    "`\"`",
+                    expected,
+                    self.chars.position(),
+                ));
+            }
+            c if !(crate::grammar::is_identifier(c) || crate::grammar::is_symbol(c)) => {
+                let expected = if c.is_alphanumeric() {
+                    "an identifier of ASCII letters, digits and `_`"
+                } else {
+                    "a meson token"
+                };
+                let found = format!("`{}`", c);
+                return Err(ParseError::unexpected(
+                    found,
+                    expected,
+                    self.chars.position(),
+                ));
+            }
+            // the first char of an operator without its left operand, meson
+            // has no unary plus
+            '+' | '*' | '/' | '%' | '<' | '>' | '=' | '!' | '?' | ':' | '.'
+                if !buff.after_operand() && !buff.span.ends_with(crate::grammar::is_symbol) =>
+            {
+                let found = format!("`{}`", c);
+                let expected = format!("an operand before `{}`", c);
+                return Err(ParseError::unexpected(
+                    found,
+                    expected,
+                    self.chars.position(),
+                ));
+            }
             '+' | '-' => {
-                buff.last_identifier =
-                    (buff.span.is_empty() || !buff.last_identifier) && buff.begin_statement;
-                buff.begin_statement = false;
+                // unary operators stick to their operand
+                buff.last_identifier = !buff.after_operand();
                 buff.merge_span_to_line();
                 buff.span.push(c);
             }
             '*' | '/' | '%' => {
                 buff.last_identifier = false;
-                buff.begin_statement = false;
                 buff.merge_span_to_line();
                 buff.span.push(c);
             }
             '.' => {
-                buff.begin_statement = false;
                 if buff.span.is_empty() {
                     buff.move_line_to_span();
                 }
                 buff.span.push(c);
             }
+            c if c.is_ascii_digit() && !buff.span.ends_with(crate::grammar::is_identifier) => {
+                buff.merge_word();
+                let number = self.parse_number(c)?;
+                buff.span.push_str(&number);
+                buff.last_identifier = true;
+            }
             c if buff.last_identifier != crate::grammar::is_identifier(c) => {
                 buff.last_identifier = crate::grammar::is_identifier(c);
-                if !buff.last_identifier {
-                    buff.begin_statement = true;
-                }
                 buff.merge_span_to_line();
                 buff.span.push(c);
             }
-            c => {
-                buff.begin_statement = false;
-                buff.span.push(c);
-            }
+            c => buff.span.push(c),
         }
         Ok(())
     }
+
+    /// meson only allows line breaks inside brackets, so a statement must not
+    /// end with an operator
+    fn check_line_end(&self, buff: &mut Buffer, found: &str) -> Result {
+        buff.merge_span_to_line();
+        match buff.dangling_operator() {
+            Some(operator) => Err(ParseError::unexpected(
+                found,
+                format!("an operand after `{}`", operator),
+                self.chars.position(),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// a statement must not begin with a binary operator either, called when
+    /// the word in `buff` is complete
+    fn check_line_start(&self, buff: &Buffer) -> Result {
+        let word = buff.span.as_str();
+        if !(buff.line.is_empty() && matches!(word, "and" | "or" | "in")) {
+            return Ok(());
+        }
+        let (line, column) = self.chars.position();
+        Err(ParseError::unexpected(
+            format!("`{}`", word),
+            format!("an operand before `{}`", word),
+            (line, column - word.len()),
+        ))
+    }
+
+    /// remove redundant parentheses from the statement in `buff` and break it
+    /// if it is longer than the line width
+    fn finish_statement(&self, buff: &mut Buffer, indent: u8, prefix: usize) {
+        buff.merge_span_to_line();
+        if self.config.remove_redundant_parens {
+            if let Some(line) = crate::parens::remove_redundant_parens(&buff.line) {
+                buff.line = line;
+            }
+        }
+        if let Some(line) =
+            crate::layout::collapse_lists(&buff.line, indent.into(), prefix, self.config)
+        {
+            buff.line = line;
+        }
+        if let Some(line) =
+            crate::layout::wrap_statement(&buff.line, indent.into(), prefix, self.config)
+        {
+            buff.line = line;
+        }
+    }
+
+    /// finish the variables and items after `foreach `
+    fn finish_foreach_header(&self, buff: &mut Buffer, indent: u8) {
+        buff.merge_span_to_line();
+        if let Some((variables, items)) = buff.line.split_once(" : ") {
+            let prefix = "foreach ".len() + variables.len() + " : ".len();
+            if let Some(items) =
+                crate::layout::collapse_lists(items, indent.into(), prefix, self.config)
+            {
+                buff.line = format!("{} : {}", variables, items);
+            }
+        }
+    }
 }
 
 impl<'a, 'b> Parser<'a, 'b> {
     /// parse comment line
     fn parse_comment(&mut self, stage: &mut String) -> Result {
-        let mut buff = String::from("# ");
-        loop {
-            match self.chars.next() {
-                None => return Ok(write!(stage, "#")?),
-                Some('\n') => return Ok(writeln!(stage, "#")?),
-                Some(' ') => {}
-                Some(c) => {
-                    buff.push(c);
-                    break;
-                }
-            }
-        }
-        loop {
-            match self.chars.next() {
-                None => return Ok(write!(stage, "{}", buff.trim_end_matches(' '))?),
-                Some('\n') => return Ok(writeln!(stage, "{}", buff.trim_end_matches(' '))?),
-                Some(c) => buff.push(c),
+        let mut text = String::new();
+        let newline = loop {
+            match self.chars.next_raw() {
+                None => break false,
+                Some('\n') => break true,
+                Some(c) => text.push(c),
             }
+        };
+        let text = text.trim_end_matches([' ', '\t', '\x0c']);
+        stage.push_str(&format_comment(text, self.config));
+        if newline {
+            stage.push('\n');
         }
+        Ok(())
     }
 
-    fn parse_string(&mut self, stage: &mut String) -> Result {
+    /// parse a string after its first quote, `format` tells whether it has
+    /// the `f` prefix
+    fn parse_string(&mut self, stage: &mut String, format: bool) -> Result {
         // single line strings
-        let parse_alone_string =
-            |chars: &mut Peekable<&mut Chars>, stage: &mut String, mut last: char| loop {
-                write!(stage, "'{}", last)?;
-                loop {
-                    match chars
-                        .next()
-                        .ok_or_else(|| ParseError::NotFindSyntaxClose("'".into()))?
-                    {
-                        '\'' if last != '\\' => {
-                            stage.push('\'');
-                            return Ok(());
-                        }
-                        c => last = c,
+        let parse_alone_string = |chars: &mut Source, stage: &mut String, first: char| -> Result {
+            write!(stage, "'{}", first)?;
+            let mut escaped = first == '\\';
+            loop {
+                match chars
+                    .next_raw()
+                    .ok_or_else(|| ParseError::NotFindSyntaxClose("'".into()))?
+                {
+                    '\'' if !escaped => {
+                        stage.push('\'');
+                        return Ok(());
+                    }
+                    c => {
+                        escaped = c == '\\' && !escaped;
+                        stage.push(c);
                     }
-                    stage.push(last);
                 }
-            };
+            }
+        };
 
-        // multi line strings
-        let parse_multi_string = |chars: &mut Peekable<&mut Chars>, stage: &mut String| {
+        // multi line strings, their content is kept as written since meson
+        // does not process escapes in them
+        let parse_multi_string = |chars: &mut Source, stage: &mut String| -> Result {
             stage.push_str("'''");
-            let mut last = '\'';
             let mut quote_count = 0;
             loop {
                 match chars
-                    .next()
+                    .next_raw()
                     .ok_or_else(|| ParseError::NotFindSyntaxClose("'''".into()))?
                 {
-                    '\'' if last != '\\' => {
-                        if quote_count == 2 {
-                            return Ok(write!(stage, "'''")?);
-                        }
-                        last = '\'';
-                        quote_count += 1;
-                    }
+                    '\'' if quote_count == 2 => return Ok(write!(stage, "'''")?),
+                    '\'' => quote_count += 1,
                     c => {
+                        // quotes which do not close the string
+                        (0..quote_count).for_each(|_| stage.push('\''));
                         stage.push(c);
-                        last = c;
                         quote_count = 0;
                     }
                 }
             }
         };
 
-        match self.chars.next() {
-            None | Some('\n') => Err(ParseError::NotFindSyntaxClose("'".into())),
+        let mut literal = String::new();
+        match self.chars.next_raw() {
+            None | Some('\n') => return Err(ParseError::NotFindSyntaxClose("'".into())),
             Some('\'') => match self.chars.peek() {
                 Some('\'') => {
                     self.chars.next();
                     // begin with three `'`
-                    parse_multi_string(&mut self.chars, stage)
+                    parse_multi_string(&mut self.chars, &mut literal)?
                 }
-                _ => Ok(write!(stage, "''")?),
+                _ => literal.push_str("''"),
             },
-            Some(c) => parse_alone_string(&mut self.chars, stage, c),
+            Some(c) => parse_alone_string(&mut self.chars, &mut literal, c)?,
+        }
+        if self.config.normalize_strings || self.config.collapse_multiline_strings {
+            literal = crate::literal::normalize_string(&literal, format, self.config);
+        }
+        if format {
+            stage.push('f');
+        }
+        stage.push_str(&literal);
+        Ok(())
+    }
+}
+
+impl<'a, 'b> Parser<'a, 'b> {
+    /// parse an integer literal after its first digit
+    fn parse_number(&mut self, first: char) -> Result<String> {
+        let position = self.chars.position();
+        let mut literal = String::from(first);
+        while let Some(&c) = self.chars.peek() {
+            if !crate::grammar::is_identifier(c) {
+                break;
+            }
+            literal.push(c);
+            self.chars.next();
+        }
+        if !crate::literal::is_integer(&literal) {
+            let found = format!("`{}`", literal);
+            return Err(ParseError::unexpected(
+                found,
+                "an integer literal",
+                position,
+            ));
+        }
+        if self.config.normalize_numbers {
+            literal = crate::literal::normalize_integer(&literal);
         }
+        Ok(literal)
     }
 }
 
 impl<'a, 'b> Parser<'a, 'b> {
+    /// parse parentheses of grouping, which are kept in one line unless
+    /// there are comments
+    fn parse_group(&mut self, stage: &mut String, indent: u8) -> Result {
+        let mut buff = Buffer::new();
+        let mut lines = vec![];
+        loop {
+            match self
+                .chars
+                .next()
+                .ok_or_else(|| ParseError::NotFindSyntaxClose("(".into()))?
+            {
+                ')' => {
+                    buff.merge_span_to_line();
+                    if lines.is_empty() {
+                        let inner_bracket_str = if self.config.space_inner_bracket {
+                            " "
+                        } else {
+                            ""
+                        };
+                        return Ok(write!(
+                            stage,
+                            "({}{}{})",
+                            inner_bracket_str, buff.line, inner_bracket_str
+                        )?);
+                    }
+                    if !buff.line.is_empty() {
+                        lines.push(buff.line);
+                    }
+                    let indent_outer_str = " ".repeat(indent.into());
+                    let indent_inner_str = " ".repeat((indent + self.config.indent_width).into());
+                    stage.push('(');
+                    for line in lines {
+                        write!(
+                            stage,
+                            "\n{}{}",
+                            indent_inner_str,
+                            line.trim_end_matches('\n')
+                        )?;
+                    }
+                    return Ok(write!(stage, "\n{})", indent_outer_str)?);
+                }
+                '#' => {
+                    buff.merge_span_to_line();
+                    buff.operand_before_comment = buff.after_operand();
+                    self.parse_comment(&mut buff.span)?;
+                    buff.merge_span_to_line();
+                    lines.push(std::mem::take(&mut buff.line));
+                }
+                '\n' | ' ' => buff.merge_word(),
+                c => self.match_char_parse(&mut buff, c, indent)?,
+            }
+        }
+    }
+
     fn parse_array(&mut self, stage: &mut String, indent: u8) -> Result {
-        self.parse_list(stage, indent, '[', ']', false)
+        self.parse_list(stage, indent, ListKind::Array, false)
     }
 
     fn parse_dictionary(&mut self, stage: &mut String, indent: u8) -> Result {
-        self.parse_list(stage, indent, '{', '}', false)
+        self.parse_list(stage, indent, ListKind::Dict, false)
     }
 
     fn parse_argument(&mut self, stage: &mut String, indent: u8) -> Result {
-        let has_name = self.config.nowrap_before_name
-            && (stage.ends_with("project")
-                || stage.ends_with("dependency")
-                || stage.ends_with("target")
-                || stage.ends_with("library")
-                || stage.ends_with("module")
-                || stage.ends_with("executable")
-                || stage.ends_with("jar")
-                || stage.ends_with("benchmark")
-                || stage.ends_with("test")
-                || stage.ends_with("add_languages")
-                || stage.ends_with("add_test_setup")
-                || stage.ends_with("subdir"));
-        self.parse_list(stage, indent, '(', ')', has_name)
+        let has_name = self.config.nowrap_before_name && takes_name(stage);
+        self.parse_list(stage, indent, ListKind::Arguments, has_name)
     }
 
     fn parse_list(
         &mut self,
         stage: &mut String,
         indent_outer: u8,
-        char_begin: char,
-        char_close: char,
+        kind: ListKind,
         has_name: bool,
     ) -> Result {
+        let (char_begin, char_close) = match kind {
+            ListKind::Array => ('[', ']'),
+            ListKind::Dict => ('{', '}'),
+            ListKind::Arguments => ('(', ')'),
+        };
+        let style = self.config.list_style(kind);
         // BUG: cann't format the comment between key and value
         let mut list = vec![];
         let mut item = (String::new(), String::new());
@@ -279,6 +548,7 @@ impl<'a, 'b> Parser<'a, 'b> {
 
         let mut multiline = false;
         let mut newline_comment = false;
+        let mut has_comment = false;
 
         let mut indent_inner = indent_outer;
 
@@ -296,7 +566,7 @@ impl<'a, 'b> Parser<'a, 'b> {
                     }
                     let key_max_length = match list.iter().map(|(k, _)| k.len()).max() {
                         None => return Ok(write!(stage, "{}{}", char_begin, char_close)?),
-                        Some(n) if self.config.space_before_colon => n + 1,
+                        Some(n) if style.space_before_colon => n + 1,
                         Some(n) => n,
                     };
                     for item in list.iter_mut() {
@@ -304,18 +574,36 @@ impl<'a, 'b> Parser<'a, 'b> {
                             item.1.pop();
                         }
                     }
+                    if style.sort_keys {
+                        sort_entries(&mut list);
+                    }
+                    let inner_bracket_str = if style.space_inner_bracket { " " } else { "" };
+                    // items without own line comments
+                    let count = list
+                        .iter()
+                        .filter(|(k, v)| !k.is_empty() || !v.starts_with('#'))
+                        .count();
+                    // a list spanning lines before other items expands this one
+                    let nested = list
+                        .split_last()
+                        .is_some_and(|(_, init)| init.iter().any(|(_, v)| v.contains('\n')));
+                    // the lines of `'''` strings can not be indented
+                    let strings = list
+                        .iter()
+                        .any(|(_, v)| v.contains("'''") && v.contains('\n'));
+                    let threshold = style.always_multiline.is_some_and(|n| count > n as usize);
+                    if !multiline && !strings && (threshold || nested) {
+                        multiline = true;
+                        indent_inner = indent_outer + self.config.indent_width;
+                        // items were indented for one line
+                        let shift = format!("\n{}", " ".repeat(self.config.indent_width.into()));
+                        for (_, v) in list.iter_mut() {
+                            *v = v.replace('\n', &shift);
+                        }
+                    }
 
-                    let indent_outer_str = std::iter::repeat(' ')
-                        .take(indent_outer.into())
-                        .collect::<String>();
-                    let indent_inner_str = std::iter::repeat(' ')
-                        .take(indent_inner.into())
-                        .collect::<String>();
-                    let inner_bracket_str = if self.config.space_inner_bracket {
-                        " "
-                    } else {
-                        ""
-                    };
+                    let indent_outer_str = " ".repeat(indent_outer.into());
+                    let indent_inner_str = " ".repeat(indent_inner.into());
 
                     let head = if multiline
                         && has_name
@@ -327,13 +615,23 @@ impl<'a, 'b> Parser<'a, 'b> {
                     } else {
                         inner_bracket_str.into()
                     };
-                    let foot = if multiline && self.config.wrap_close_brace {
+                    let foot = if multiline && style.wrap_close_brace {
                         format!("\n{}", indent_outer_str)
                     } else {
                         inner_bracket_str.into()
                     };
 
-                    let merge_key_value = if self.config.align_colon {
+                    // an array on one line which does not fit is filled by
+                    // `layout::wrap_statement`, which knows its column
+                    let fill = multiline
+                        && style.fill
+                        && !has_comment
+                        && list.iter().all(|(k, v)| {
+                            let v = v.strip_suffix(',').unwrap_or(v);
+                            k.is_empty() && crate::literal::is_simple_literal(v)
+                        });
+
+                    let merge_key_value = if style.align_colon {
                         |a: String, b: String, key_max_length| {
                             format!("{:w$}: {}", a, b, w = key_max_length)
                         }
@@ -350,7 +648,7 @@ impl<'a, 'b> Parser<'a, 'b> {
                             }
                         })
                         .collect::<Vec<_>>();
-                    if !multiline || !self.config.wrap_close_brace {
+                    if !multiline || !style.wrap_close_brace {
                         if let Some(last) = items.last_mut() {
                             if last.ends_with(',') {
                                 last.pop();
@@ -358,7 +656,11 @@ impl<'a, 'b> Parser<'a, 'b> {
                         }
                     }
 
-                    let body = if multiline {
+                    let body = if fill {
+                        let width =
+                            (self.config.line_width as usize).saturating_sub(indent_inner.into());
+                        fill_lines(items, width).join(&(String::from('\n') + &indent_inner_str))
+                    } else if multiline {
                         items.join(&(String::from('\n') + &indent_inner_str))
                     } else {
                         items.join(" ")
@@ -372,21 +674,35 @@ impl<'a, 'b> Parser<'a, 'b> {
                 }
                 '#' => {
                     buff.move_line_to_stage(&mut item.1);
-                    if !item.0.is_empty() || !item.1.is_empty() {
-                        item.1.push(',');
-                        list.push(item);
-                        item = (String::new(), String::new());
-                    } else if newline_comment {
-                        list.push(item);
-                        item = (String::new(), String::new());
-                    }
-                    if let Some(buff) = list.last_mut() {
-                        if !buff.1.is_empty() {
-                            buff.1.push(' ');
+                    let mut comment = String::new();
+                    self.parse_comment(&mut comment)?;
+                    let unfinished = !item.0.is_empty() || !item.1.is_empty();
+                    buff.operand_before_comment = unfinished && self.continues_item(char_close);
+                    if buff.operand_before_comment {
+                        // an operator on the next line continues the item
+                        let indent = indent_outer + 2 * self.config.indent_width;
+                        write!(item.1, " {}{}", comment, " ".repeat(indent.into()))?;
+                    } else {
+                        if unfinished {
+                            item.1.push(',');
+                            list.push(item);
+                            item = (String::new(), String::new());
+                        } else if newline_comment || list.is_empty() {
+                            list.push(item);
+                            item = (String::new(), String::new());
+                        }
+                        if let Some(buff) = list.last_mut() {
+                            if !buff.1.is_empty() {
+                                buff.1.push(' ');
+                            }
+                            buff.1.push_str(&comment);
                         }
-                        self.parse_comment(&mut buff.1)?;
                     }
+                    // the comment ends its line
+                    multiline = true;
+                    has_comment = true;
                     newline_comment = true;
+                    indent_inner = indent_outer + self.config.indent_width;
                 }
                 ':' => {
                     buff.move_line_to_stage(&mut item.0);
@@ -394,6 +710,7 @@ impl<'a, 'b> Parser<'a, 'b> {
                 }
                 ',' => {
                     buff.move_line_to_stage(&mut item.1);
+                    buff.operand_before_comment = false;
                     if !item.0.is_empty() || !item.1.is_empty() {
                         item.1.push(',');
                         list.push(item);
@@ -408,23 +725,50 @@ impl<'a, 'b> Parser<'a, 'b> {
                     newline_comment = true;
                     indent_inner = indent_outer + self.config.indent_width;
                 }
-                ' ' => buff.merge_span_to_line(),
+                ' ' => buff.merge_word(),
                 c => self.match_char_parse(&mut buff, c, indent_inner)?,
             }
         }
     }
 }
 
+/// whether the first argument of `function` is a name, which stays on the
+/// line of the call when its arguments are written one per line
+pub(crate) fn takes_name(function: &str) -> bool {
+    [
+        "project",
+        "dependency",
+        "target",
+        "library",
+        "module",
+        "executable",
+        "jar",
+        "benchmark",
+        "test",
+        "add_languages",
+        "add_test_setup",
+        "subdir",
+    ]
+    .iter()
+    .any(|name| function.ends_with(name))
+}
+
 impl<'a, 'b> Parser<'a, 'b> {
+    /// skip the whitespace after a comment in a list, the item before it
+    /// goes on unless a comma, the close of the list or another comment
+    /// follows
+    fn continues_item(&mut self, close: char) -> bool {
+        while let Some(' ' | '\t' | '\x0c' | '\n') = self.chars.peek() {
+            self.chars.next();
+        }
+        !matches!(self.chars.peek(), Some(&c) if c == close || c == ',' || c == '#')
+    }
+
     fn parse_if_statement(&mut self, stage: &mut String, indent_outer: u8) -> Result {
         let indent_inner = indent_outer + self.config.indent_width;
 
-        let indent_outer_str = std::iter::repeat(' ')
-            .take(indent_outer.into())
-            .collect::<String>();
-        let indent_inner_str = std::iter::repeat(' ')
-            .take(indent_inner.into())
-            .collect::<String>();
+        let indent_outer_str = " ".repeat(indent_outer.into());
+        let indent_inner_str = " ".repeat(indent_inner.into());
 
         let mut buff = Buffer::new();
 
@@ -448,8 +792,9 @@ impl<'a, 'b> Parser<'a, 'b> {
                 }
                 None => return Err(ParseError::NotFindSyntaxClose("if".into())),
                 Some('#') => {
-                    buff.merge_span_to_line();
+                    self.check_line_end(&mut buff, "comment")?;
                     self.parse_comment(&mut buff.span)?;
+                    self.finish_if_branch(&mut buff, first_line, indent_outer);
                     buff.move_line_to_stage_with_indent(
                         stage,
                         &mut first_line,
@@ -457,12 +802,11 @@ impl<'a, 'b> Parser<'a, 'b> {
                     );
                 }
                 Some('\n') => {
-                    match buff.span.as_ref() {
-                        "foreach" => self.parse_for_statement(&mut buff.span, indent_inner)?,
-                        "if" => self.parse_if_statement(&mut buff.span, indent_inner)?,
-                        "endforeach" => return Err(ParseError::InvalidSyntaxClose(buff.span)),
+                    self.check_line_end(&mut buff, "end of line")?;
+                    match buff.line.as_ref() {
+                        "endforeach" => return Err(ParseError::InvalidSyntaxClose(buff.line)),
                         "endif" => return Ok(writeln!(stage, "{}endif", indent_outer_str)?),
-                        _ => {}
+                        _ => self.finish_if_branch(&mut buff, first_line, indent_outer),
                     }
                     buff.move_line_to_stage_with_indent(
                         stage,
@@ -490,7 +834,10 @@ impl<'a, 'b> Parser<'a, 'b> {
                     }
                     "endforeach" => return Err(ParseError::InvalidSyntaxClose(buff.span)),
                     "endif" => return Ok(write!(stage, "{}endif", indent_outer_str)?),
-                    _ => buff.merge_span_to_line(),
+                    _ => {
+                        self.check_line_start(&buff)?;
+                        buff.merge_word()
+                    }
                 },
                 Some(c) => self.match_char_parse(&mut buff, c, indent_inner)?,
             }
@@ -499,12 +846,8 @@ impl<'a, 'b> Parser<'a, 'b> {
     fn parse_for_statement(&mut self, stage: &mut String, indent_outer: u8) -> Result {
         let indent_inner = indent_outer + self.config.indent_width;
 
-        let indent_outer_str = std::iter::repeat(' ')
-            .take(indent_outer.into())
-            .collect::<String>();
-        let indent_inner_str = std::iter::repeat(' ')
-            .take(indent_inner.into())
-            .collect::<String>();
+        let indent_outer_str = " ".repeat(indent_outer.into());
+        let indent_inner_str = " ".repeat(indent_inner.into());
 
         let mut buff = Buffer::new();
 
@@ -521,8 +864,13 @@ impl<'a, 'b> Parser<'a, 'b> {
                 }
                 None => return Err(ParseError::NotFindSyntaxClose("foreach".into())),
                 Some('#') => {
-                    buff.merge_span_to_line();
+                    self.check_line_end(&mut buff, "comment")?;
                     self.parse_comment(&mut buff.span)?;
+                    if first_line {
+                        self.finish_foreach_header(&mut buff, indent_outer);
+                    } else {
+                        self.finish_statement(&mut buff, indent_inner, 0);
+                    }
                     buff.move_line_to_stage_with_indent(
                         stage,
                         &mut first_line,
@@ -530,14 +878,14 @@ impl<'a, 'b> Parser<'a, 'b> {
                     );
                 }
                 Some('\n') => {
-                    match buff.span.as_ref() {
-                        "foreach" => self.parse_for_statement(&mut buff.span, indent_inner)?,
-                        "if" => self.parse_if_statement(&mut buff.span, indent_inner)?,
+                    self.check_line_end(&mut buff, "end of line")?;
+                    match buff.line.as_ref() {
                         "endforeach" => {
                             return Ok(writeln!(stage, "{}endforeach", indent_outer_str)?)
                         }
-                        "endif" => return Err(ParseError::InvalidSyntaxClose(buff.span)),
-                        _ => {}
+                        "endif" => return Err(ParseError::InvalidSyntaxClose(buff.line)),
+                        _ if !first_line => self.finish_statement(&mut buff, indent_inner, 0),
+                        _ => self.finish_foreach_header(&mut buff, indent_outer),
                     }
                     buff.move_line_to_stage_with_indent(
                         stage,
@@ -565,11 +913,13 @@ impl<'a, 'b> Parser<'a, 'b> {
                     }
                     "endforeach" => return Ok(writeln!(stage, "{}endforeach", indent_outer_str)?),
                     "endif" => return Err(ParseError::InvalidSyntaxClose(buff.span)),
-                    _ => buff.merge_span_to_line(),
+                    _ => {
+                        self.check_line_start(&buff)?;
+                        buff.merge_word()
+                    }
                 },
                 Some(',') => {
                     buff.last_identifier = false;
-                    buff.begin_statement = true;
                     buff.span.push(',');
                     buff.merge_span_to_line();
                 }
@@ -577,6 +927,19 @@ impl<'a, 'b> Parser<'a, 'b> {
             }
         }
     }
+
+    /// finish the condition or a statement of the body of `if`
+    fn finish_if_branch(&self, buff: &mut Buffer, first_line: bool, indent_outer: u8) {
+        buff.merge_span_to_line();
+        if first_line {
+            // after `if `
+            self.finish_statement(buff, indent_outer, 3);
+        } else if buff.line.starts_with("elif ") {
+            self.finish_statement(buff, indent_outer, 0);
+        } else {
+            self.finish_statement(buff, indent_outer + self.config.indent_width, 0);
+        }
+    }
 }
 
 struct Buffer {
@@ -584,18 +947,72 @@ struct Buffer {
     line: String,
     /// a word or symbol
     span: String,
-    /// last char is identifier, literal
+    /// last char is identifier, literal, or the span is an unary operator
     last_identifier: bool,
-    /// start new statement, ("+" | "-") unary_operator identity
-    begin_statement: bool,
+    /// the line before a comment ended with an operand
+    operand_before_comment: bool,
+}
+
+/// sort entries of a dictionary by their keys, comment lines stay above
+/// the entry after them
+fn sort_entries(list: &mut Vec<(String, String)>) {
+    let mut groups = vec![];
+    let mut group = vec![];
+    for item in list.drain(..) {
+        let keyed = !item.0.is_empty();
+        group.push(item);
+        if keyed {
+            groups.push(std::mem::take(&mut group));
+        }
+    }
+    groups.sort_by(|a, b| a[a.len() - 1].0.cmp(&b[b.len() - 1].0));
+    list.extend(groups.into_iter().flatten());
+    // comments after the last entry
+    list.extend(group);
+}
+
+/// pack items into lines of at most `width` chars, an item longer than that
+/// gets a line of its own
+pub(crate) fn fill_lines(items: Vec<String>, width: usize) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+    for item in items {
+        match lines.last_mut() {
+            Some(line) if line.chars().count() + 1 + item.chars().count() <= width => {
+                line.push(' ');
+                line.push_str(&item);
+            }
+            _ => lines.push(item),
+        }
+    }
+    lines
+}
+
+/// the comment for `text` after `#`
+fn format_comment(text: &str, config: &Configuration) -> String {
+    let content = text.trim_start_matches([' ', '\t', '\x0c']);
+    if content.is_empty() {
+        return "#".into();
+    }
+    let preserve = config.comment_style == CommentStyle::Preserve;
+    let untouched =
+        // banners like `####` or `#-`, and `#!`
+        text.starts_with(['#', '-', '=', '*', '!'])
+        || config.comment_prefixes.iter().any(|prefix| content.starts_with(prefix.as_str()))
+        || (preserve && content.len() < text.len());
+    if untouched {
+        format!("#{}", text)
+    } else {
+        format!("# {}", content)
+    }
 }
+
 impl Buffer {
     fn new() -> Self {
         Self {
             line: String::new(),
             span: String::new(),
             last_identifier: false,
-            begin_statement: true,
+            operand_before_comment: false,
         }
     }
     fn merge_span_to_line(&mut self) {
@@ -607,6 +1024,60 @@ impl Buffer {
             self.span.clear();
         }
     }
+    /// merge span to line at a space, unless it is an unary operator waiting
+    /// for its operand
+    fn merge_word(&mut self) {
+        if !(self.last_identifier && self.span == "-") {
+            self.merge_span_to_line();
+        }
+    }
+    /// remove `f` before a quote from the span when it is the prefix of a
+    /// format string rather than the end of an identifier
+    fn take_format_prefix(&mut self) -> bool {
+        let format = match self.span.strip_suffix('f') {
+            Some(rest) => !rest.ends_with(crate::grammar::is_identifier),
+            None => false,
+        };
+        if format {
+            self.span.pop();
+        }
+        format
+    }
+    /// the operator or keyword at the end of the statement which misses its
+    /// operand
+    fn dangling_operator(&self) -> Option<&str> {
+        let word = self.last_word();
+        let dangling = matches!(
+            word,
+            "not" | "and" | "or" | "in" | "if" | "elif" | "foreach"
+        ) || word.ends_with(|c| "+-*/%=!<>?:,.".contains(c));
+        dangling.then_some(word)
+    }
+    /// the last word of span or line
+    fn last_word(&self) -> &str {
+        let text = if self.span.is_empty() {
+            &self.line
+        } else {
+            &self.span
+        };
+        text.rsplit(' ').next().unwrap_or_default()
+    }
+    /// `(` after the span calls a function or method
+    fn is_call(&self) -> bool {
+        let word = self.last_word();
+        self.last_identifier
+            && word.ends_with(crate::grammar::is_identifier)
+            && !crate::grammar::is_keyword(word)
+    }
+    /// the last word ends an operand, so `+` or `-` is a binary operator
+    fn after_operand(&self) -> bool {
+        if self.line.is_empty() && self.span.is_empty() {
+            return self.operand_before_comment;
+        }
+        let word = self.last_word();
+        word.ends_with(|c| crate::grammar::is_identifier(c) || matches!(c, '\'' | ')' | ']' | '}'))
+            && !crate::grammar::is_keyword(word)
+    }
     fn move_line_to_span(&mut self) {
         std::mem::swap(&mut self.line, &mut self.span);
         self.line.clear();
@@ -627,7 +1098,7 @@ impl Buffer {
         self.merge_span_to_line();
         if *first_line {
             *first_line = false;
-        } else {
+        } else if !self.line.is_empty() {
             push_indent_from_line(stage, &self.line);
         }
         stage.push_str(&self.line);
@@ -639,7 +1110,6 @@ impl Buffer {
 mod tests {
     use super::*;
     use crate::configuration::DEFAULT_CONFIGURATION;
-    use std::ops::Deref;
 
     #[test]
     fn merge_buffer() {
@@ -680,13 +1150,19 @@ mod tests {
     fn parse_string() {
         let mut stage = String::new();
 
-        for (chars, expected) in &[("text'", "'text'"), ("''text'''", "'''text'''")] {
+        for (chars, format, expected) in &[
+            ("text'", false, "'text'"),
+            ("''text'''", false, "'''text'''"),
+            ("''a\\''' + 'b'", false, "'''a\\'''"),
+            ("''it''s'''", false, "'''it''s'''"),
+            ("''@a@\n  b'''", true, "f'''@a@\n  b'''"),
+        ] {
             let mut chars = chars.chars();
             stage.clear();
             Parser::new(&DEFAULT_CONFIGURATION, &mut chars)
-                .parse_string(&mut stage)
+                .parse_string(&mut stage, *format)
                 .unwrap();
-            assert_eq!(stage, expected.deref());
+            assert_eq!(stage, *expected);
         }
     }
 
@@ -703,7 +1179,7 @@ mod tests {
             Parser::new(&DEFAULT_CONFIGURATION, &mut chars)
                 .parse_array(&mut stage, 0)
                 .unwrap();
-            assert_eq!(stage, expected.deref());
+            assert_eq!(stage, *expected);
         }
     }
 
@@ -720,7 +1196,7 @@ mod tests {
             Parser::new(&DEFAULT_CONFIGURATION, &mut chars)
                 .parse_dictionary(&mut stage, 0)
                 .unwrap();
-            assert_eq!(stage, expected.deref());
+            assert_eq!(stage, *expected);
         }
     }
 
@@ -738,7 +1214,7 @@ mod tests {
             Parser::new(&DEFAULT_CONFIGURATION, &mut chars)
                 .parse_argument(&mut stage, 0)
                 .unwrap();
-            assert_eq!(stage, expected.deref());
+            assert_eq!(stage, *expected);
         }
     }
 }
@@ -0,0 +1,304 @@
+use crate::ast::{self, Expr, ExprKind, Span, Visitor};
+use crate::checker::{check_path, project_meson_version, Diagnostic};
+use crate::configuration::Configuration;
+use crate::format_text::format_text;
+use crate::parser::ParseError;
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+const BUILD_FILE: &str = "meson.build";
+
+/// build files of a project tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Project {
+    pub root: PathBuf,
+    /// `meson.build` files reached from the top level one by `subdir()` in
+    /// the order meson reads them, then the other ones under the root
+    pub files: Vec<ProjectFile>,
+    /// `subdir()` calls naming a directory without `meson.build`
+    pub missing: Vec<MissingFile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectFile {
+    /// path relative to the root
+    pub path: PathBuf,
+    /// `subdir()` reaches the file
+    pub reached: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingFile {
+    /// the missing `meson.build` relative to the root
+    pub path: PathBuf,
+    /// the file calling `subdir()`
+    pub from: PathBuf,
+    /// span of the call
+    pub span: Span,
+}
+
+/// result of formatting or checking one file of a project
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileReport {
+    /// path relative to the root
+    pub path: PathBuf,
+    pub reached: bool,
+    pub outcome: Outcome,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// the formatted text, `None` when the file is already formatted
+    Formatted(Option<String>),
+    Checked(Vec<Diagnostic>),
+    ParseError(ParseError),
+    IoError(String),
+}
+
+/// Find the build files of the project at `root`, following `subdir()`
+/// calls with a string argument from `root/meson.build`.
+///
+/// Hidden directories and `subprojects`, which hold projects of their own,
+/// are not searched for files `subdir()` does not reach. Files which can not
+/// be read or parsed are listed without following their `subdir()` calls.
+pub fn discover_project(root: &Path) -> Project {
+    let mut project = Project {
+        root: root.to_path_buf(),
+        files: vec![],
+        missing: vec![],
+    };
+    let mut reached = HashSet::new();
+    let mut pending = vec![PathBuf::from(BUILD_FILE)];
+    pending.retain(|path| root.join(path).is_file());
+    // depth first, in the order of the calls
+    while let Some(path) = pending.pop() {
+        if !reached.insert(path.clone()) {
+            continue;
+        }
+        let dir = path.parent().unwrap_or_else(|| Path::new("")).to_path_buf();
+        let calls = fs::read_to_string(root.join(&path))
+            .ok()
+            .and_then(|text| ast::parse_file(&text).ok())
+            .map(|file| {
+                let mut finder = SubdirFinder { calls: vec![] };
+                ast::walk_statements(&mut finder, &file.statements);
+                finder.calls
+            })
+            .unwrap_or_default();
+        let mut children = vec![];
+        for (name, span) in calls {
+            let child = dir.join(name).join(BUILD_FILE);
+            if root.join(&child).is_file() {
+                children.push(child);
+            } else {
+                project.missing.push(MissingFile {
+                    path: child,
+                    from: path.clone(),
+                    span,
+                });
+            }
+        }
+        pending.extend(children.into_iter().rev());
+        project.files.push(ProjectFile {
+            path,
+            reached: true,
+        });
+    }
+
+    let mut stray = vec![];
+    find_build_files(root, Path::new(""), &mut stray);
+    stray.retain(|path| !reached.contains(path));
+    stray.sort();
+    project
+        .files
+        .extend(stray.into_iter().map(|path| ProjectFile {
+            path,
+            reached: false,
+        }));
+    project
+}
+
+/// `meson.build` files under `root/dir`, relative to `root`
+fn find_build_files(root: &Path, dir: &Path, files: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(root.join(dir)) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let path = dir.join(&name);
+        let file_type = match entry.file_type() {
+            Ok(file_type) => file_type,
+            Err(_) => continue,
+        };
+        if file_type.is_dir() {
+            let name = name.to_string_lossy();
+            if !name.starts_with('.') && name != "subprojects" {
+                find_build_files(root, &path, files);
+            }
+        } else if name == BUILD_FILE {
+            files.push(path);
+        }
+    }
+}
+
+/// `subdir('name')` calls of a file
+pub(crate) struct SubdirFinder {
+    pub calls: Vec<(String, Span)>,
+}
+
+impl Visitor for SubdirFinder {
+    fn visit_expr(&mut self, expr: &Expr) -> bool {
+        if let ExprKind::Call { name, arguments } = &expr.kind {
+            if name.name == "subdir" {
+                if let Some(dir) = arguments.positional.first().and_then(|e| e.kind.as_str()) {
+                    self.calls.push((dir.to_string(), expr.span));
+                }
+            }
+        }
+        true
+    }
+}
+
+/// format every file of the project in parallel, nothing is written
+pub fn format_project(project: &Project, config: &Configuration) -> Vec<FileReport> {
+    parallel_map(&project.files, |file| {
+        let outcome = match fs::read_to_string(project.root.join(&file.path)) {
+            Ok(text) => match format_text(&text, config) {
+                Ok(formatted) => Outcome::Formatted(formatted.filter(|t| *t != text)),
+                Err(e) => Outcome::ParseError(e),
+            },
+            Err(e) => Outcome::IoError(e.to_string()),
+        };
+        report(file, outcome)
+    })
+}
+
+/// check every file of the project in parallel, files reached by `subdir()`
+/// are checked against the `meson_version` of the top level `project()`
+pub fn check_project(project: &Project) -> Vec<FileReport> {
+    let meson_version = fs::read_to_string(project.root.join(BUILD_FILE))
+        .ok()
+        .and_then(|text| ast::parse_file(&text).ok())
+        .and_then(|file| project_meson_version(&file));
+    parallel_map(&project.files, |file| {
+        let path = project.root.join(&file.path);
+        let version = meson_version.as_ref().filter(|_| file.reached);
+        let outcome = match fs::read_to_string(&path) {
+            Ok(text) => match ast::parse_file(&text) {
+                Ok(ast) => Outcome::Checked(check_path(&path, &ast, version)),
+                Err(e) => Outcome::ParseError(e),
+            },
+            Err(e) => Outcome::IoError(e.to_string()),
+        };
+        report(file, outcome)
+    })
+}
+
+fn report(file: &ProjectFile, outcome: Outcome) -> FileReport {
+    FileReport {
+        path: file.path.clone(),
+        reached: file.reached,
+        outcome,
+    }
+}
+
+/// `f` of every item on all cores, in the order of the items
+fn parallel_map<T: Sync, R: Send>(items: &[T], f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    let workers = thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(items.len());
+    let next = AtomicUsize::new(0);
+    let mut results = thread::scope(|scope| {
+        let handles = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut results = vec![];
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        match items.get(index) {
+                            Some(item) => results.push((index, f(item))),
+                            None => return results,
+                        }
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::DEFAULT_CONFIGURATION;
+
+    fn root() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/project")
+    }
+
+    #[test]
+    fn discover_tree() {
+        let project = discover_project(&root());
+        let files = project
+            .files
+            .iter()
+            .map(|f| (f.path.to_str().unwrap(), f.reached))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            files,
+            [
+                ("meson.build", true),
+                ("src/meson.build", true),
+                ("src/broken/meson.build", true),
+                ("docs/meson.build", true),
+                ("stray/meson.build", false),
+            ]
+        );
+        assert_eq!(project.missing.len(), 1);
+        assert_eq!(project.missing[0].path, Path::new("tests/meson.build"));
+        assert_eq!(project.missing[0].from, Path::new("meson.build"));
+        assert_eq!(project.missing[0].span.start.line, 7);
+
+        assert_eq!(discover_project(&root().join("none")).files, []);
+    }
+
+    #[test]
+    fn format_and_check_tree() {
+        let project = discover_project(&root());
+        let outcomes = format_project(&project, &DEFAULT_CONFIGURATION)
+            .into_iter()
+            .map(|r| r.outcome)
+            .collect::<Vec<_>>();
+        assert!(matches!(
+            outcomes.as_slice(),
+            [
+                Outcome::Formatted(None),
+                Outcome::Formatted(Some(_)),
+                Outcome::ParseError(_),
+                Outcome::Formatted(None),
+                Outcome::Formatted(None),
+            ]
+        ));
+
+        let reports = check_project(&project);
+        let messages = |i: usize| match &reports[i].outcome {
+            Outcome::Checked(diagnostics) => diagnostics.len(),
+            outcome => panic!("{:?}", outcome),
+        };
+        // format strings are newer than the `meson_version` of the project
+        assert_eq!(messages(3), 1);
+        assert_eq!(messages(4), 0);
+        assert!(matches!(reports[2].outcome, Outcome::ParseError(_)));
+    }
+}
@@ -0,0 +1,348 @@
+use crate::ast::{self, Arguments, Expr, ExprKind, Span, StringLiteral, Visitor};
+use crate::parser::ParseError;
+use std::{error::Error, fmt};
+
+/// replace the text in `span` with `new_text`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub span: Span,
+    pub new_text: String,
+}
+
+/// apply edits which do not overlap each other
+pub fn apply_edits(text: &str, edits: &[TextEdit]) -> String {
+    let mut edits = edits.iter().collect::<Vec<_>>();
+    edits.sort_by_key(|e| e.span.start.offset);
+    let mut result = text.to_string();
+    for edit in edits.into_iter().rev() {
+        result.replace_range(edit.span.start.offset..edit.span.end.offset, &edit.new_text);
+    }
+    result
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StringStyle {
+    /// `'@0@'.format(a)`
+    Format,
+    /// `f'@a@'`
+    FString,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConversionError {
+    /// nothing to convert at the given offset
+    NotFound,
+    /// `.format()` is not called on a string literal
+    NotLiteral,
+    /// `.format()` has keyword arguments
+    KeywordArgument,
+    /// an argument of `.format()` is not a plain identifier
+    NotIdentifier(Span),
+    /// `@N@` has no matching argument
+    MissingArgument(usize),
+    /// the placeholder means something else in the other style, such as
+    /// `@name@` in a `.format()` string or `@0@` in an f-string
+    PlaceholderConflict(String),
+    Parse(ParseError),
+}
+impl Error for ConversionError {}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NotFound => write!(f, "no `.format()` call or f-string found"),
+            Self::NotLiteral => write!(f, "`.format()` is not called on a string literal"),
+            Self::KeywordArgument => write!(f, "`.format()` has keyword arguments"),
+            Self::NotIdentifier(span) => write!(
+                f,
+                "argument at {}:{} is not a plain identifier",
+                span.start.line, span.start.column
+            ),
+            Self::MissingArgument(n) => write!(f, "no argument for placeholder `@{}@`", n),
+            Self::PlaceholderConflict(p) => {
+                write!(f, "`@{}@` would change meaning after conversion", p)
+            }
+            Self::Parse(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<ParseError> for ConversionError {
+    fn from(e: ParseError) -> Self {
+        Self::Parse(e)
+    }
+}
+
+/// convert the innermost `.format()` call or f-string around `offset`
+pub fn convert_string_at(
+    file_text: &str,
+    offset: usize,
+    to: StringStyle,
+) -> Result<TextEdit, ConversionError> {
+    let file = ast::parse_file(file_text)?;
+    let mut finder = Finder {
+        to,
+        found: vec![],
+        offset: Some(offset),
+    };
+    ast::walk_statements(&mut finder, &file.statements);
+    let expr = finder.found.pop().ok_or(ConversionError::NotFound)?;
+    convert(file_text, &expr, to)
+}
+
+/// Convert every `.format()` call or f-string of the file, returns the new
+/// text and the strings which could not be converted.
+pub fn convert_strings(
+    file_text: &str,
+    to: StringStyle,
+) -> Result<(String, Vec<(Span, ConversionError)>), ParseError> {
+    let file = ast::parse_file(file_text)?;
+    let mut finder = Finder {
+        to,
+        found: vec![],
+        offset: None,
+    };
+    ast::walk_statements(&mut finder, &file.statements);
+
+    let mut edits = vec![];
+    let mut failures = vec![];
+    for expr in finder.found {
+        match convert(file_text, &expr, to) {
+            Ok(edit) => edits.push(edit),
+            Err(e) => failures.push((expr.span, e)),
+        }
+    }
+    Ok((apply_edits(file_text, &edits), failures))
+}
+
+/// convert a `.format()` call or an f-string expression
+pub fn convert(source: &str, expr: &Expr, to: StringStyle) -> Result<TextEdit, ConversionError> {
+    let new_text = match (&expr.kind, to) {
+        (
+            ExprKind::MethodCall {
+                object,
+                name,
+                arguments,
+            },
+            StringStyle::FString,
+        ) if name.name == "format" => format_to_fstring(source, object, arguments)?,
+        (ExprKind::String(s), StringStyle::Format) if s.format => {
+            fstring_to_format(expr.span.text(source), s)?
+        }
+        _ => return Err(ConversionError::NotFound),
+    };
+    Ok(TextEdit {
+        span: expr.span,
+        new_text,
+    })
+}
+
+/// split the text between quotes at `@...@` pairs, calls `placeholder` with
+/// the text between a pair
+fn replace_placeholders(
+    content: &str,
+    mut placeholder: impl FnMut(&str) -> Result<Option<String>, ConversionError>,
+) -> Result<String, ConversionError> {
+    let mut result = String::new();
+    let mut rest = content;
+    while let Some(begin) = rest.find('@') {
+        result.push_str(&rest[..begin]);
+        rest = &rest[begin + 1..];
+        let name = match rest.find('@') {
+            Some(end) => &rest[..end],
+            None => {
+                result.push('@');
+                continue;
+            }
+        };
+        match placeholder(name)? {
+            Some(replacement) => {
+                result.push_str(&replacement);
+                rest = &rest[name.len() + 1..];
+            }
+            None => result.push('@'),
+        }
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+fn is_name(text: &str) -> bool {
+    !text.is_empty()
+        && !text.starts_with(|c: char| c.is_ascii_digit())
+        && text.chars().all(crate::grammar::is_identifier)
+}
+
+fn is_index(text: &str) -> bool {
+    !text.is_empty() && text.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// `'@0@-@1@'.format(a, b)` to `f'@a@-@b@'`
+fn format_to_fstring(
+    source: &str,
+    object: &Expr,
+    arguments: &Arguments,
+) -> Result<String, ConversionError> {
+    if !matches!(&object.kind, ExprKind::String(s) if !s.format) {
+        return Err(ConversionError::NotLiteral);
+    }
+    if !arguments.keywords.is_empty() {
+        return Err(ConversionError::KeywordArgument);
+    }
+    let names = arguments
+        .positional
+        .iter()
+        .map(|e| {
+            e.kind
+                .as_identifier()
+                .ok_or(ConversionError::NotIdentifier(e.span))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // keep quotes and escapes as written
+    let literal = object.span.text(source);
+    let quote = if literal.starts_with("'''") { 3 } else { 1 };
+    let content = &literal[quote..literal.len() - quote];
+    let content = replace_placeholders(content, |p| {
+        if is_index(p) {
+            let index = p.parse::<usize>().unwrap_or(usize::MAX);
+            let name = names
+                .get(index)
+                .ok_or(ConversionError::MissingArgument(index))?;
+            Ok(Some(format!("@{}@", name)))
+        } else if is_name(p) {
+            Err(ConversionError::PlaceholderConflict(p.into()))
+        } else {
+            Ok(None)
+        }
+    })?;
+    let quote = &literal[..quote];
+    Ok(format!("f{}{}{}", quote, content, quote))
+}
+
+/// `f'@a@-@b@'` to `'@0@-@1@'.format(a, b)`
+fn fstring_to_format(literal: &str, string: &StringLiteral) -> Result<String, ConversionError> {
+    let quote = if string.multiline { 3 } else { 1 };
+    let content = &literal[1 + quote..literal.len() - quote];
+    let mut names: Vec<String> = vec![];
+    let content = replace_placeholders(content, |p| {
+        if is_name(p) {
+            let index = match names.iter().position(|n| n == p) {
+                Some(i) => i,
+                None => {
+                    names.push(p.into());
+                    names.len() - 1
+                }
+            };
+            Ok(Some(format!("@{}@", index)))
+        } else if is_index(p) {
+            Err(ConversionError::PlaceholderConflict(p.into()))
+        } else {
+            Ok(None)
+        }
+    })?;
+    let quote = &literal[1..1 + quote];
+    let mut result = format!("{}{}{}", quote, content, quote);
+    if !names.is_empty() {
+        result.push_str(&format!(".format({})", names.join(", ")));
+    }
+    Ok(result)
+}
+
+/// collect convertible expressions, outermost first
+struct Finder {
+    to: StringStyle,
+    found: Vec<Expr>,
+    /// only expressions around this offset
+    offset: Option<usize>,
+}
+
+impl Visitor for Finder {
+    fn visit_expr(&mut self, expr: &Expr) -> bool {
+        if self.offset.is_some_and(|o| !expr.span.contains(o)) {
+            return false;
+        }
+        let matched = match (&expr.kind, self.to) {
+            (ExprKind::MethodCall { name, .. }, StringStyle::FString) => name.name == "format",
+            (ExprKind::String(s), StringStyle::Format) => s.format,
+            _ => false,
+        };
+        if matched {
+            self.found.push(expr.clone());
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert_to_fstring() {
+        let text =
+            "a = '@0@/@1@-@0@'.format(x, y)\nb = '''@0@\n'''.format(z)\nc = 'x@y'.format()\n";
+        let (converted, failures) = convert_strings(text, StringStyle::FString).unwrap();
+        assert_eq!(
+            converted,
+            "a = f'@x@/@y@-@x@'\nb = f'''@z@\n'''\nc = f'x@y'\n"
+        );
+        assert!(failures.is_empty());
+
+        let (converted, failures) =
+            convert_strings(converted.as_str(), StringStyle::Format).unwrap();
+        assert_eq!(
+            converted,
+            "a = '@0@/@1@-@0@'.format(x, y)\nb = '''@0@\n'''.format(z)\nc = 'x@y'\n"
+        );
+        assert!(failures.is_empty());
+    }
+
+    #[test]
+    fn convert_failures() {
+        for (text, error) in [
+            (
+                "'@0@'.format(a.b())",
+                ConversionError::NotIdentifier(Span::default()),
+            ),
+            ("'@1@'.format(a)", ConversionError::MissingArgument(1)),
+            (
+                "'@0@ @b@'.format(a)",
+                ConversionError::PlaceholderConflict("b".into()),
+            ),
+            ("'@0@'.format(a, b: 1)", ConversionError::KeywordArgument),
+            ("x.format(a)", ConversionError::NotLiteral),
+        ] {
+            let result = convert_string_at(text, 1, StringStyle::FString);
+            match (result, error) {
+                (Err(ConversionError::NotIdentifier(_)), ConversionError::NotIdentifier(_)) => {}
+                (result, error) => assert_eq!(result, Err(error), "{}", text),
+            }
+        }
+        assert_eq!(
+            convert_string_at("f'@0@ @a@'", 2, StringStyle::Format),
+            Err(ConversionError::PlaceholderConflict("0".into()))
+        );
+        assert_eq!(
+            convert_string_at("a = 'x'", 5, StringStyle::Format),
+            Err(ConversionError::NotFound)
+        );
+        assert_eq!(
+            convert_strings("f'@0@'", StringStyle::Format)
+                .unwrap()
+                .1
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn convert_at_offset() {
+        let text = "x = f'@a@'.strip() + '@0@'.format(b)";
+        let edit = convert_string_at(text, 25, StringStyle::FString).unwrap();
+        assert_eq!(edit.new_text, "f'@b@'");
+        assert_eq!(edit.span.text(text), "'@0@'.format(b)");
+        let edit = convert_string_at(text, 6, StringStyle::Format).unwrap();
+        assert_eq!(edit.new_text, "'@0@'.format(a)");
+    }
+}
@@ -0,0 +1,190 @@
+use crate::ast::{self, Span};
+use crate::configuration::{CommentStyle, Configuration};
+use crate::refactor::{apply_edits, TextEdit};
+
+/// Reflow blocks of comment lines in formatted text to the line width,
+/// returns the text unchanged when it can not be parsed.
+pub fn reflow_comments(text: &str, config: &Configuration) -> String {
+    let comments = match ast::parse_comments(text) {
+        Ok(comments) => comments,
+        Err(_) => return text.to_string(),
+    };
+    // comments alone on their line with their indentation
+    let lines = comments
+        .iter()
+        .filter_map(|comment| {
+            let line_start = text[..comment.start.offset]
+                .rfind('\n')
+                .map_or(0, |i| i + 1);
+            let indent = &text[line_start..comment.start.offset];
+            indent.trim().is_empty().then_some((*comment, indent))
+        })
+        .collect::<Vec<_>>();
+
+    let mut edits = vec![];
+    let mut rest = lines.as_slice();
+    while let Some((_, indent)) = rest.first() {
+        let len = 1 + rest
+            .windows(2)
+            .take_while(|pair| {
+                pair[0].0.start.line + 1 == pair[1].0.start.line && pair[0].1 == pair[1].1
+            })
+            .count();
+        let (block, tail) = rest.split_at(len);
+        rest = tail;
+        let span = Span {
+            start: block[0].0.start,
+            end: block[len - 1].0.end,
+        };
+        let comments = block.iter().map(|(c, _)| c.text(text)).collect::<Vec<_>>();
+        let width = (config.line_width as usize).saturating_sub(indent.chars().count());
+        let new_text = reflow_block(&comments, width, config).join(&format!("\n{}", indent));
+        if new_text != span.text(text) {
+            edits.push(TextEdit { span, new_text });
+        }
+    }
+    apply_edits(text, &edits)
+}
+
+/// a paragraph or list item being filled
+struct Paragraph<'a> {
+    /// `# ` and the list marker
+    first: String,
+    /// whitespace after `#` of continuation lines
+    hang: usize,
+    words: Vec<&'a str>,
+}
+
+/// reflow comment lines of one block
+fn reflow_block(lines: &[&str], width: usize, config: &Configuration) -> Vec<String> {
+    // the formatter gave the hanging lines of list items one space
+    let normalized = config.comment_style != CommentStyle::Preserve;
+    let mut result = vec![];
+    let mut paragraph: Option<Paragraph> = None;
+    for line in lines {
+        let body = &line[1..];
+        let content = body.trim_start_matches(' ');
+        let indent = body.len() - content.len();
+        let kept = content.is_empty()
+            // banners, `#!` and code-like or indented lines
+            || indent != 1
+            || content.contains("dprint-ignore")
+            || config
+                .comment_prefixes
+                .iter()
+                .any(|prefix| content.starts_with(prefix.as_str()));
+        let marker = list_marker(content);
+
+        if let Some(p) = &mut paragraph {
+            let continued = if p.hang == 1 || (normalized && !kept) {
+                !kept && marker.is_none()
+            } else {
+                !content.is_empty() && indent == p.hang && !content.contains("dprint-ignore")
+            };
+            if continued {
+                p.words.extend(content.split_whitespace());
+                continue;
+            }
+        }
+        if let Some(p) = paragraph.take() {
+            fill(p, width, &mut result);
+        }
+        if kept {
+            result.push(line.to_string());
+            continue;
+        }
+        let marker = marker.unwrap_or("");
+        paragraph = Some(Paragraph {
+            first: format!("# {}", marker),
+            hang: 1 + marker.len(),
+            words: content[marker.len()..].split_whitespace().collect(),
+        });
+    }
+    if let Some(p) = paragraph {
+        fill(p, width, &mut result);
+    }
+    result
+}
+
+/// `- `, `* `, `+ `, `1. ` or `1) ` at the beginning of a list item
+fn list_marker(content: &str) -> Option<&str> {
+    let digits = content.chars().take_while(|c| c.is_ascii_digit()).count();
+    let len = match content[digits..].chars().next()? {
+        '-' | '*' | '+' if digits == 0 => 1,
+        '.' | ')' if digits > 0 => digits + 1,
+        _ => return None,
+    };
+    content[len..].starts_with(' ').then(|| &content[..len + 1])
+}
+
+/// fill lines with the words of a paragraph, words longer than the line like
+/// URLs are never broken
+fn fill(paragraph: Paragraph, width: usize, result: &mut Vec<String>) {
+    let hang = format!("#{}", " ".repeat(paragraph.hang));
+    let mut line = paragraph.first;
+    let mut empty = true;
+    for word in paragraph.words {
+        if !empty && line.chars().count() + 1 + word.chars().count() > width {
+            result.push(std::mem::replace(&mut line, hang.clone()));
+            empty = true;
+        }
+        if !empty {
+            line.push(' ');
+        }
+        line.push_str(word);
+        empty = false;
+    }
+    result.push(line);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::DEFAULT_CONFIGURATION;
+
+    #[test]
+    fn reflow_blocks() {
+        let config = Configuration {
+            line_width: 20,
+            ..DEFAULT_CONFIGURATION
+        };
+        for (src, dst) in [
+            ("# a b c\n# d e\n", "# a b c d e\n"),
+            (
+                "# one two three four five six\n",
+                "# one two three four\n# five six\n",
+            ),
+            (
+                "  # aaaa bbbb cccc dddd\nx = 1\n",
+                "  # aaaa bbbb cccc\n  # dddd\nx = 1\n",
+            ),
+            ("# a\n#\n# b\n", "# a\n#\n# b\n"),
+            ("# a\n\n# b\n", "# a\n\n# b\n"),
+            (
+                "# list:\n# - one two three four\n# - five\n",
+                "# list:\n# - one two three\n#   four\n# - five\n",
+            ),
+            (
+                "# 1. aaaa bbbb cccc\n#    dddd\n",
+                "# 1. aaaa bbbb cccc\n#    dddd\n",
+            ),
+            (
+                "# see\n# https://example.com/a/long/path\n",
+                "# see\n# https://example.com/a/long/path\n",
+            ),
+            (
+                "# code:\n#   f(a, b) + g(c, d)\n# x\n",
+                "# code:\n#   f(a, b) + g(c, d)\n# x\n",
+            ),
+            ("#####\n# a\n# b\n", "#####\n# a b\n"),
+            ("# dprint-ignore\n# a\n", "# dprint-ignore\n# a\n"),
+            ("x = 1 # a b c d e f g h\n", "x = 1 # a b c d e f g h\n"),
+            (
+                "x = [\n  # aaaa bbbb cccc dddd\n  1,\n]\n",
+                "x = [\n  # aaaa bbbb cccc\n  # dddd\n  1,\n]\n",
+            ),
+        ] {
+            assert_eq!(reflow_comments(src, &config), dst, "{}", src);
+        }
+    }
+}
@@ -0,0 +1,476 @@
+use crate::ast::{self, Expr, ExprKind, File, Position, Span, Statement, Visitor};
+use crate::configuration::Configuration;
+use crate::format_text::format_text;
+use crate::parser::ParseError;
+use crate::project::discover_project;
+use crate::refactor::{apply_edits, TextEdit};
+use serde_json::{json, Map, Value};
+use std::{error::Error, fmt, fs, path::Path, path::PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RenameError {
+    /// the new name is not an identifier or is a keyword
+    InvalidName(String),
+    /// a variable of the new name exists already
+    NameInUse(String),
+    /// a build file can not be parsed, `None` for the given text
+    Parse(Option<PathBuf>, ParseError),
+    /// a build file can not be read
+    Io(PathBuf, String),
+    /// the parameters of a `textDocument/rename` request are not understood
+    InvalidRequest(String),
+}
+impl Error for RenameError {}
+
+impl fmt::Display for RenameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::InvalidName(name) => write!(f, "`{}` is not a valid variable name", name),
+            Self::NameInUse(name) => write!(f, "a variable named `{}` exists already", name),
+            Self::Parse(Some(path), e) => write!(f, "{}: {}", path.display(), e),
+            Self::Parse(None, e) => write!(f, "{}", e),
+            Self::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            Self::InvalidRequest(e) => write!(f, "invalid rename request: {}", e),
+        }
+    }
+}
+
+/// a build file changed by a rename
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenamedFile {
+    /// path relative to the project root
+    pub path: PathBuf,
+    /// the formatted text with the new name
+    pub text: String,
+}
+
+/// where a variable name appears
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Occurrence {
+    pub span: Span,
+    /// assigned by `=`, `+=` or `foreach`
+    pub definition: bool,
+}
+
+/// Rename a variable in one file, returns the new text.
+///
+/// Only identifiers and `@name@` placeholders of f-strings are changed, not
+/// strings, keyword argument names, functions or methods.
+pub fn rename_text(file_text: &str, old: &str, new: &str) -> Result<String, RenameError> {
+    check_name(new)?;
+    let file = ast::parse_file(file_text).map_err(|e| RenameError::Parse(None, e))?;
+    if !occurrences(file_text, &file, new).is_empty() {
+        return Err(RenameError::NameInUse(new.into()));
+    }
+    Ok(replace(file_text, &file, old, new))
+}
+
+/// Rename a variable in every `meson.build` of the project at `root` reached
+/// by `subdir()`, since they share one scope. Returns the changed files
+/// formatted with `config`, nothing is written. Every file has to parse.
+pub fn rename(
+    root: &Path,
+    old: &str,
+    new: &str,
+    config: &Configuration,
+) -> Result<Vec<RenamedFile>, RenameError> {
+    check_name(new)?;
+    let mut files = vec![];
+    for file in discover_project(root).files {
+        if !file.reached {
+            continue;
+        }
+        let text = fs::read_to_string(root.join(&file.path))
+            .map_err(|e| RenameError::Io(file.path.clone(), e.to_string()))?;
+        let ast =
+            ast::parse_file(&text).map_err(|e| RenameError::Parse(Some(file.path.clone()), e))?;
+        if !occurrences(&text, &ast, new).is_empty() {
+            return Err(RenameError::NameInUse(new.into()));
+        }
+        files.push((file.path, text, ast));
+    }
+
+    let mut renamed = vec![];
+    for (path, text, ast) in files {
+        let replaced = replace(&text, &ast, old, new);
+        if replaced == text {
+            continue;
+        }
+        let formatted = format_text(&replaced, config)
+            .map_err(|e| RenameError::Parse(Some(path.clone()), e))?;
+        renamed.push(RenamedFile {
+            path,
+            text: formatted.unwrap_or(replaced),
+        });
+    }
+    Ok(renamed)
+}
+
+/// Answer an LSP `textDocument/rename` request for a build file of the
+/// project at `root`. The variable at the position of `params` is renamed
+/// with `rename` and the result is returned as a `WorkspaceEdit` replacing
+/// the whole text of each changed file, or `null` when there is no variable
+/// at the position. Like in LSP, lines are counted from 0 and characters in
+/// UTF-16 code units. Files are read from disk, so unsaved changes are not
+/// seen.
+pub fn rename_request(
+    root: &Path,
+    params: &Value,
+    config: &Configuration,
+) -> Result<Value, RenameError> {
+    let invalid = |what: &str| RenameError::InvalidRequest(format!("no {}", what));
+    let uri = params["textDocument"]["uri"]
+        .as_str()
+        .ok_or_else(|| invalid("textDocument.uri"))?;
+    let line = params["position"]["line"]
+        .as_u64()
+        .ok_or_else(|| invalid("position.line"))?;
+    let character = params["position"]["character"]
+        .as_u64()
+        .ok_or_else(|| invalid("position.character"))?;
+    let new = params["newName"]
+        .as_str()
+        .ok_or_else(|| invalid("newName"))?;
+
+    let root = fs::canonicalize(root).map_err(|e| RenameError::Io(root.into(), e.to_string()))?;
+    let document = path_from_uri(uri).ok_or_else(|| invalid("file uri"))?;
+    let text = fs::read_to_string(&document)
+        .map_err(|e| RenameError::Io(document.clone(), e.to_string()))?;
+    let path = fs::canonicalize(&document)
+        .ok()
+        .and_then(|p| Some(p.strip_prefix(&root).ok()?.to_path_buf()))
+        .ok_or_else(|| RenameError::InvalidRequest(format!("{} is not in the project", uri)))?;
+    let offset = offset_at(&text, line as usize, character as usize);
+    let variable = crate::navigation::variable_at(&text, offset)
+        .map_err(|e| RenameError::Parse(Some(path), e))?;
+    let old = match variable {
+        Some((old, _)) => old,
+        None => return Ok(Value::Null),
+    };
+
+    let mut changes = Map::new();
+    for file in rename(&root, &old, new, config)? {
+        let old_text = fs::read_to_string(root.join(&file.path))
+            .map_err(|e| RenameError::Io(file.path.clone(), e.to_string()))?;
+        let (end_line, end_character) = lsp_position(&old_text, old_text.len());
+        let edit = json!({
+            "range": {
+                "start": { "line": 0, "character": 0 },
+                "end": { "line": end_line, "character": end_character },
+            },
+            "newText": file.text,
+        });
+        changes.insert(uri_from_path(&root.join(&file.path)), json!([edit]));
+    }
+    Ok(json!({ "changes": changes }))
+}
+
+/// byte offset of an LSP position, clamped to the end of its line
+fn offset_at(text: &str, line: usize, character: usize) -> usize {
+    let start = match line {
+        0 => 0,
+        _ => match text.match_indices('\n').nth(line - 1) {
+            Some((i, _)) => i + 1,
+            None => return text.len(),
+        },
+    };
+    let mut units = 0;
+    for (i, c) in text[start..].char_indices() {
+        if c == '\n' || units >= character {
+            return start + i;
+        }
+        units += c.len_utf16();
+    }
+    text.len()
+}
+
+/// LSP line and UTF-16 character of a byte offset
+fn lsp_position(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset];
+    let start = before.rfind('\n').map_or(0, |i| i + 1);
+    let character = before[start..].chars().map(char::len_utf16).sum();
+    (before.matches('\n').count(), character)
+}
+
+/// the path of a `file://` URI with percent escapes decoded
+fn path_from_uri(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?.as_bytes();
+    let mut bytes = vec![];
+    let mut i = 0;
+    while i < encoded.len() {
+        if encoded[i] == b'%' {
+            let hex = std::str::from_utf8(encoded.get(i + 1..i + 3)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            bytes.push(encoded[i]);
+            i += 1;
+        }
+    }
+    Some(PathBuf::from(String::from_utf8(bytes).ok()?))
+}
+
+/// `file://` URI of an absolute path, escaping bytes other than unreserved
+/// characters and `/`
+fn uri_from_path(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for b in path.to_string_lossy().bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                uri.push(b as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    uri
+}
+
+fn check_name(name: &str) -> Result<(), RenameError> {
+    let valid = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(crate::grammar::is_identifier)
+        && !crate::grammar::is_reserved(name);
+    if valid {
+        Ok(())
+    } else {
+        Err(RenameError::InvalidName(name.into()))
+    }
+}
+
+fn replace(text: &str, file: &File, old: &str, new: &str) -> String {
+    let edits = occurrences(text, file, old)
+        .into_iter()
+        .map(|o| TextEdit {
+            span: o.span,
+            new_text: new.into(),
+        })
+        .collect::<Vec<_>>();
+    apply_edits(text, &edits)
+}
+
+/// the occurrences of the variable `name` in a file, in the order of the text
+pub(crate) fn occurrences(text: &str, file: &File, name: &str) -> Vec<Occurrence> {
+    let mut finder = OccurrenceFinder {
+        text,
+        name,
+        found: vec![],
+    };
+    ast::walk_statements(&mut finder, &file.statements);
+    finder.found.sort_by_key(|o| o.span.start.offset);
+    finder.found
+}
+
+struct OccurrenceFinder<'a> {
+    text: &'a str,
+    name: &'a str,
+    found: Vec<Occurrence>,
+}
+
+impl OccurrenceFinder<'_> {
+    fn push(&mut self, span: Span, definition: bool) {
+        self.found.push(Occurrence { span, definition });
+    }
+
+    /// `@name@` placeholders in the text of an f-string
+    fn placeholders(&mut self, expr: &Expr) {
+        let raw = expr.span.text(self.text);
+        let mut rest = 0;
+        while let Some(at) = raw[rest..].find('@').map(|i| rest + i) {
+            let len = raw[at + 1..]
+                .find(|c: char| !crate::grammar::is_identifier(c))
+                .unwrap_or(raw.len() - at - 1);
+            let word = &raw[at + 1..at + 1 + len];
+            let closed = raw[at + 1 + len..].starts_with('@');
+            if !closed || word.is_empty() || word.starts_with(|c: char| c.is_ascii_digit()) {
+                rest = at + 1;
+                continue;
+            }
+            if word == self.name {
+                // placeholders can not span lines
+                let start = Position {
+                    offset: expr.span.start.offset + at + 1,
+                    column: expr.span.start.column + raw[..at + 1].chars().count(),
+                    ..expr.span.start
+                };
+                let start = match raw[..at].rfind('\n') {
+                    Some(newline) => Position {
+                        line: expr.span.start.line + raw[..at].matches('\n').count(),
+                        column: raw[newline + 1..at + 1].chars().count() + 1,
+                        ..start
+                    },
+                    None => start,
+                };
+                let end = Position {
+                    offset: start.offset + len,
+                    column: start.column + len,
+                    ..start
+                };
+                self.push(Span { start, end }, false);
+            }
+            rest = at + 2 + len;
+        }
+    }
+}
+
+impl Visitor for OccurrenceFinder<'_> {
+    fn visit_statement(&mut self, statement: &Statement) -> bool {
+        match statement {
+            Statement::Assignment { target, .. } if target.name == self.name => {
+                self.push(target.span, true)
+            }
+            Statement::Foreach { variables, .. } => {
+                let name = self.name;
+                for variable in variables.iter().filter(|v| v.name == name) {
+                    self.push(variable.span, true);
+                }
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) -> bool {
+        match &expr.kind {
+            ExprKind::Identifier(name) if name == self.name => self.push(expr.span, false),
+            ExprKind::String(s) if s.format => self.placeholders(expr),
+            _ => {}
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::DEFAULT_CONFIGURATION;
+
+    #[test]
+    fn rename_variable() {
+        let text = "foo_dep = dependency('foo_dep')
+foo_dep += [x]
+executable('foo_dep', dependencies: foo_dep, foo_dep: 1)
+foreach foo_dep : foo_dep.list()
+  message(f'@foo_dep@ @foo_dep_x@ @@foo_dep@')
+endforeach
+";
+        assert_eq!(
+            rename_text(text, "foo_dep", "bar").unwrap(),
+            "bar = dependency('foo_dep')
+bar += [x]
+executable('foo_dep', dependencies: bar, foo_dep: 1)
+foreach bar : bar.list()
+  message(f'@bar@ @foo_dep_x@ @@bar@')
+endforeach
+"
+        );
+        for name in ["1a", "a-b", "if", "true", ""] {
+            assert_eq!(
+                rename_text(text, "foo_dep", name),
+                Err(RenameError::InvalidName(name.into()))
+            );
+        }
+        assert_eq!(
+            rename_text(text, "foo_dep", "x"),
+            Err(RenameError::NameInUse("x".into()))
+        );
+    }
+
+    #[test]
+    fn placeholder_positions() {
+        let text = "x = f'''a\n b @foo@'''";
+        let file = ast::parse_file(text).unwrap();
+        let found = occurrences(text, &file, "foo");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].span.text(text), "foo");
+        assert_eq!(
+            (found[0].span.start.line, found[0].span.start.column),
+            (2, 5)
+        );
+    }
+
+    #[test]
+    fn rename_in_project() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data");
+        let renamed = rename(
+            &root.join("rename"),
+            "lib",
+            "demo_lib",
+            &DEFAULT_CONFIGURATION,
+        );
+        assert_eq!(
+            renamed.unwrap(),
+            [
+                RenamedFile {
+                    path: "meson.build".into(),
+                    text: "project('demo', 'c')\n\ndemo_lib = library('demo', 'a.c')\nsubdir('sub')\nmessage(f'@demo_lib@')\n".into(),
+                },
+                RenamedFile {
+                    path: "sub/meson.build".into(),
+                    text: "exe = executable('app', 'main.c', link_with: demo_lib)\ntest('lib', exe)\n".into(),
+                },
+            ]
+        );
+
+        let result = rename(
+            &root.join("project"),
+            "lib",
+            "demo_lib",
+            &DEFAULT_CONFIGURATION,
+        );
+        // `src/broken/meson.build` does not parse
+        assert!(matches!(result, Err(RenameError::Parse(Some(_), _))));
+    }
+
+    #[test]
+    fn rename_requests() {
+        let root =
+            fs::canonicalize(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/rename"))
+                .unwrap();
+        let uri = uri_from_path(&root.join("sub/meson.build"));
+        let request = |line: usize, character: usize| {
+            json!({
+                "textDocument": { "uri": uri },
+                "position": { "line": line, "character": character },
+                "newName": "demo_lib",
+            })
+        };
+        let edit = rename_request(&root, &request(0, 46), &DEFAULT_CONFIGURATION).unwrap();
+        let changes = edit["changes"].as_object().unwrap();
+        assert_eq!(
+            changes.keys().collect::<Vec<_>>(),
+            [&uri_from_path(&root.join("meson.build")), &uri]
+        );
+        assert_eq!(
+            changes[&uri],
+            json!([{
+                "range": {
+                    "start": { "line": 0, "character": 0 },
+                    "end": { "line": 2, "character": 0 },
+                },
+                "newText": "exe = executable('app', 'main.c', link_with: demo_lib)\ntest('lib', exe)\n",
+            }])
+        );
+        // inside `'lib'`
+        let edit = rename_request(&root, &request(1, 7), &DEFAULT_CONFIGURATION).unwrap();
+        assert_eq!(edit, Value::Null);
+
+        let result = rename_request(&root, &json!({}), &DEFAULT_CONFIGURATION);
+        assert!(matches!(result, Err(RenameError::InvalidRequest(_))));
+    }
+
+    #[test]
+    fn lsp_positions() {
+        // `😀` is two UTF-16 code units
+        let text = "a = 'é😀'\nb = a\n";
+        assert_eq!(lsp_position(text, text.find("'\n").unwrap()), (0, 8));
+        assert_eq!(lsp_position(text, text.len()), (2, 0));
+        assert_eq!(offset_at(text, 0, 8), text.find("'\n").unwrap());
+        assert_eq!(offset_at(text, 1, 4), text.rfind('a').unwrap());
+        assert_eq!(offset_at(text, 1, 99), text.len() - 1);
+        assert_eq!(offset_at(text, 9, 0), text.len());
+
+        let path = Path::new("/a b/é/meson.build");
+        assert_eq!(uri_from_path(path), "file:///a%20b/%C3%A9/meson.build");
+        assert_eq!(path_from_uri(&uri_from_path(path)).unwrap(), path);
+        assert_eq!(path_from_uri("http://a/meson.build"), None);
+    }
+}
@@ -0,0 +1,161 @@
+use crate::version::Version;
+use serde::Deserialize;
+use std::{collections::BTreeMap, sync::OnceLock};
+
+/// bundled table of meson built-in functions and object methods
+pub fn database() -> &'static Database {
+    static DATABASE: OnceLock<Database> = OnceLock::new();
+    DATABASE.get_or_init(|| {
+        serde_json::from_str(include_str!("signatures.json"))
+            .expect("bundled signatures.json is invalid")
+    })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Database {
+    /// keyword arguments shared by several functions
+    groups: BTreeMap<String, BTreeMap<String, KwargSpec>>,
+    pub functions: BTreeMap<String, Signature>,
+    /// methods by object type
+    pub objects: BTreeMap<String, BTreeMap<String, Signature>>,
+    /// object type of built-in variables
+    pub variables: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Signature {
+    /// minimum and maximum (`null` for unlimited) count of positional arguments
+    pub args: (usize, Option<usize>),
+    #[serde(default)]
+    groups: Vec<String>,
+    #[serde(default)]
+    kwargs: BTreeMap<String, KwargSpec>,
+    pub since: Option<String>,
+    pub deprecated: Option<String>,
+    pub replacement: Option<String>,
+    /// object type of the result
+    pub returns: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum KwargSpec {
+    /// types joined by `|`
+    Types(String),
+    Full {
+        #[serde(rename = "type")]
+        types: String,
+        since: Option<String>,
+        deprecated: Option<String>,
+        replacement: Option<String>,
+        #[serde(default)]
+        required: bool,
+    },
+}
+
+impl KwargSpec {
+    pub fn types(&self) -> impl Iterator<Item = &str> {
+        match self {
+            Self::Types(types) | Self::Full { types, .. } => types.split('|'),
+        }
+    }
+    pub fn since(&self) -> Option<Version> {
+        match self {
+            Self::Full { since, .. } => since.as_ref()?.parse().ok(),
+            _ => None,
+        }
+    }
+    pub fn deprecated(&self) -> Option<(Version, Option<&str>)> {
+        match self {
+            Self::Full {
+                deprecated,
+                replacement,
+                ..
+            } => Some((deprecated.as_ref()?.parse().ok()?, replacement.as_deref())),
+            _ => None,
+        }
+    }
+    pub fn required(&self) -> bool {
+        matches!(self, Self::Full { required: true, .. })
+    }
+}
+
+impl Signature {
+    pub fn since(&self) -> Option<Version> {
+        self.since.as_ref()?.parse().ok()
+    }
+    pub fn deprecated(&self) -> Option<Version> {
+        self.deprecated.as_ref()?.parse().ok()
+    }
+
+    /// every accepted keyword argument, names may be patterns like `*_args`
+    pub fn kwargs<'a>(&'a self, database: &'a Database) -> Vec<(&'a str, &'a KwargSpec)> {
+        let mut kwargs = self
+            .groups
+            .iter()
+            .filter_map(|g| database.groups.get(g))
+            .flatten()
+            .chain(&self.kwargs)
+            .map(|(k, v)| (k.as_str(), v))
+            .collect::<Vec<_>>();
+        // later entries override the shared groups
+        kwargs.reverse();
+        let mut seen = std::collections::BTreeSet::new();
+        kwargs.retain(|(k, _)| seen.insert(*k));
+        kwargs
+    }
+
+    pub fn kwarg<'a>(&'a self, database: &'a Database, name: &str) -> Option<&'a KwargSpec> {
+        self.kwargs(database)
+            .into_iter()
+            .find(|(pattern, _)| match pattern.strip_prefix('*') {
+                Some(suffix) => name.ends_with(suffix) && name.len() > suffix.len(),
+                None => *pattern == name,
+            })
+            .map(|(_, spec)| spec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_database() {
+        let db = database();
+        let executable = &db.functions["executable"];
+        assert_eq!(executable.args, (1, None));
+        assert!(executable.kwarg(db, "link_with").is_some());
+        assert!(executable.kwarg(db, "cpp_args").is_some());
+        assert!(executable.kwarg(db, "_args").is_none());
+        assert!(executable.kwarg(db, "link_wiht").is_none());
+        assert_eq!(
+            executable.kwarg(db, "pie").unwrap().since(),
+            Some(Version(vec![0, 49, 0]))
+        );
+        assert!(db.functions["custom_target"]
+            .kwarg(db, "output")
+            .unwrap()
+            .required());
+
+        for signature in db
+            .functions
+            .values()
+            .chain(db.objects.values().flat_map(|o| o.values()))
+        {
+            assert!(signature.groups.iter().all(|g| db.groups.contains_key(g)));
+            for version in [&signature.since, &signature.deprecated] {
+                assert!(version.as_ref().map(|v| v.parse::<Version>().is_ok()) != Some(false));
+            }
+            for (_, spec) in signature.kwargs(db) {
+                if let KwargSpec::Full {
+                    since, deprecated, ..
+                } = spec
+                {
+                    assert_eq!(since.is_some(), spec.since().is_some());
+                    assert_eq!(deprecated.is_some(), spec.deprecated().is_some());
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use std::{cmp::Ordering, fmt, str::FromStr};
+
+/// meson release number, such as `0.56.0`
+#[derive(Debug, Clone, Default)]
+pub struct Version(pub Vec<u32>);
+
+impl FromStr for Version {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.trim()
+            .split('.')
+            .map(|n| {
+                n.parse::<u32>()
+                    .map_err(|_| format!("invalid version: {}", s))
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(Version)
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let length = self.0.len().max(other.0.len());
+        (0..length)
+            .map(|i| {
+                let a = self.0.get(i).copied().unwrap_or(0);
+                let b = other.0.get(i).copied().unwrap_or(0);
+                a.cmp(&b)
+            })
+            .find(|o| *o != Ordering::Equal)
+            .unwrap_or(Ordering::Equal)
+    }
+}
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+impl Eq for Version {}
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Less,
+    LessEqual,
+    Equal,
+    NotEqual,
+    GreaterEqual,
+    Greater,
+}
+
+/// a single comparison such as `>=0.56.0`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Constraint {
+    pub operator: Operator,
+    pub version: Version,
+}
+
+impl Constraint {
+    pub fn matches(&self, version: &Version) -> bool {
+        let ordering = version.cmp(&self.version);
+        match self.operator {
+            Operator::Less => ordering == Ordering::Less,
+            Operator::LessEqual => ordering != Ordering::Greater,
+            Operator::Equal => ordering == Ordering::Equal,
+            Operator::NotEqual => ordering != Ordering::Equal,
+            Operator::GreaterEqual => ordering != Ordering::Less,
+            Operator::Greater => ordering == Ordering::Greater,
+        }
+    }
+}
+
+impl FromStr for Constraint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (operator, version) = [
+            (">=", Operator::GreaterEqual),
+            ("<=", Operator::LessEqual),
+            ("==", Operator::Equal),
+            ("!=", Operator::NotEqual),
+            (">", Operator::Greater),
+            ("<", Operator::Less),
+            ("=", Operator::Equal),
+        ]
+        .iter()
+        .find_map(|(prefix, operator)| Some((*operator, s.strip_prefix(prefix)?)))
+        .unwrap_or((Operator::Equal, s));
+        Ok(Constraint {
+            operator,
+            version: version.parse()?,
+        })
+    }
+}
+
+/// every constraint has to match, as `project(meson_version: ...)` accepts
+/// a string or a list of strings
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct VersionRequirement(pub Vec<Constraint>);
+
+impl VersionRequirement {
+    pub fn matches(&self, version: &Version) -> bool {
+        self.0.iter().all(|c| c.matches(version))
+    }
+
+    /// lowest version allowed by the lower bounds, which decides what
+    /// features may be used
+    pub fn minimum(&self) -> Option<Version> {
+        self.0
+            .iter()
+            .filter(|c| {
+                matches!(
+                    c.operator,
+                    Operator::GreaterEqual | Operator::Greater | Operator::Equal
+                )
+            })
+            .map(|c| c.version.clone())
+            .max()
+    }
+}
+
+impl FromStr for VersionRequirement {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .map(VersionRequirement)
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let parts = self.0.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        write!(f, "{}", parts.join("."))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compare_version() {
+        let v = |s: &str| s.parse::<Version>().unwrap();
+        assert!(v("0.56.0") > v("0.49"));
+        assert!(v("1.0") == v("1.0.0"));
+        assert!(v("0.9.1") < v("0.10"));
+    }
+
+    #[test]
+    fn parse_requirement() {
+        let v = |s: &str| s.parse::<Version>().unwrap();
+        let r = |s: &str| s.parse::<VersionRequirement>().unwrap();
+        assert_eq!(r(">= 0.56.0").minimum(), Some(v("0.56.0")));
+        assert_eq!(r("<1.0").minimum(), None);
+        assert_eq!(r(">=0.50, >0.55, <2").minimum(), Some(v("0.55")));
+        assert!(r(">=0.50, <2").matches(&v("1.3.0")));
+        assert!(!r(">=0.50, <2").matches(&v("2.0")));
+        assert!(r("0.60").matches(&v("0.60.0")));
+        assert!(r("!=1.0").matches(&v("1.1")));
+        assert!("x".parse::<VersionRequirement>().is_err());
+    }
+}
@@ -29,7 +29,11 @@ impl SyncPluginHandler<Configuration> for MesonPluginHandler {
             },
             file_matching: FileMatchingInfo {
                 file_extensions: vec![],
-                file_names: vec!["meson.build".to_string(), "meson_options.txt".to_string()],
+                file_names: vec![
+                    "meson.build".to_string(),
+                    "meson_options.txt".to_string(),
+                    "meson.options".to_string(),
+                ],
             },
         }
     }
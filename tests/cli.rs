@@ -0,0 +1,41 @@
+use std::{path::Path, process::Command};
+
+fn run(args: &[&str]) -> (bool, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_mesonbuild"))
+        .args(args)
+        .current_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data"))
+        .output()
+        .unwrap();
+    (
+        output.status.success(),
+        String::from_utf8(output.stdout).unwrap(),
+    )
+}
+
+#[test]
+fn inventory_table() {
+    let (success, table) = run(&["inventory", "project"]);
+    assert!(success);
+    assert_eq!(
+        table,
+        "FILE              LINE  KIND          NAMES    VERSION  REQUIRED  FALLBACK  METHOD  CONDITIONS\n\
+         docs/meson.build  4     find_program  doxygen                                       get_option('docs')\n"
+    );
+}
+
+#[test]
+fn inventory_json() {
+    let (success, json) = run(&["inventory", "--json", "project"]);
+    assert!(success);
+    let value = serde_json::from_str::<serde_json::Value>(&json).unwrap();
+    assert_eq!(value["entries"][0]["file"], "docs/meson.build");
+    assert_eq!(value["entries"][0]["names"][0], "doxygen");
+}
+
+#[test]
+fn usage_errors() {
+    assert!(!run(&[]).0);
+    assert!(!run(&["inventory", "--yaml", "project"]).0);
+    assert!(!run(&["inventory", "project", "docs"]).0);
+    assert!(!run(&["inventory", "none"]).0);
+}
@@ -12,18 +12,17 @@ fn format_with_host(_: &Path, _: String, _: &ConfigKeyMap) -> FormatResult {
     Ok(Some("".to_string()))
 }
 
-fn assert_with_config(expected: &str, toml_config: &str) {
+fn format_with_config(content: &str, toml_config: &str) -> String {
     let config = toml::from_str(toml_config).unwrap();
 
-    let result = MesonPluginHandler::default()
-        .format(
-            &Path::new("meson.build"),
-            ORIGIN_CONTENT,
-            &config,
-            format_with_host,
-        )
+    MesonPluginHandler::default()
+        .format(Path::new("meson.build"), content, &config, format_with_host)
+        .unwrap()
         .unwrap()
-        .unwrap();
+}
+
+fn assert_with_config(expected: &str, toml_config: &str) {
+    let result = format_with_config(ORIGIN_CONTENT, toml_config);
 
     for (a, b) in result.lines().zip(expected.lines()) {
         assert_eq!(a, b);
@@ -52,3 +51,26 @@ fn format_file_2() {
         include_str!("data/2/config.toml"),
     );
 }
+
+#[test]
+fn format_file_3() {
+    // CRLF with a byte order mark
+    let expected = include_str!("data/3/meson.build");
+    let config = include_str!("data/3/config.toml");
+    assert_eq!(format_with_config(expected, config), expected);
+    assert_eq!(
+        format_with_config(ORIGIN_CONTENT, config),
+        expected.trim_start_matches('\u{feff}')
+    );
+    assert_eq!(format_with_config(expected, CONFIG_CONTENT), ORIGIN_CONTENT);
+}
+
+#[test]
+fn format_file_4() {
+    // indented with tabs, tabs and form feeds between tokens
+    let expected = include_str!("data/4/meson.build");
+    let config = include_str!("data/4/config.toml");
+    let input = include_str!("data/4/input.build");
+    assert_eq!(format_with_config(input, config), expected);
+    assert_eq!(format_with_config(expected, config), expected);
+}